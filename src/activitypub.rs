@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use axum::extract::{Extension, Path, Query};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::store::{FeedEntry, Store};
+use crate::HttpError;
+
+/// Timeout applied to every outbound ActivityPub request (actor document
+/// fetches and signed inbox deliveries). A malicious remote actor's
+/// `inbox`/actor URL is otherwise free to hang the request forever, wedging
+/// whichever follower-notification task or inbox handler is awaiting it.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shared client for every outbound ActivityPub request, per the rationale
+/// in `cache.rs`: one `Client` reused across calls, rather than a bare
+/// `Client::new()` (and its missing timeout) built fresh each time.
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .expect("reqwest client with a timeout can always be built")
+});
+
+/// The externally-visible base URL (e.g. `http://rexml.mattjhall.xyz`) that
+/// actor, inbox and outbox ids are built from. `rexml`'s lib modules don't
+/// otherwise know their own address -- [`crate::websub`] is handed fully
+/// formed URLs by the caller -- but ActivityPub needs it in enough places
+/// (WebFinger, the actor document, outbox pages) that it's threaded through
+/// as an extension instead.
+#[derive(Debug, Clone)]
+pub struct BaseUrl(pub Arc<str>);
+
+/// A remote actor's inbox and public key, as fetched from its actor
+/// document.
+#[derive(Debug, Clone)]
+struct RemoteActor {
+    inbox: String,
+    shared_inbox: Option<String>,
+    public_key_pem: String,
+}
+
+struct CachedActor {
+    actor: RemoteActor,
+    fetched_at: Instant,
+}
+
+/// TTL cache of fetched remote actors, keyed by actor URL, so that verifying
+/// a `Follow`'s signature or delivering to the same follower repeatedly
+/// doesn't refetch its public key on every request.
+pub struct ActorCache {
+    client: reqwest::Client,
+    entries: RwLock<HashMap<String, CachedActor>>,
+    ttl: Duration,
+}
+
+impl ActorCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            client: CLIENT.clone(),
+            entries: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    async fn get(&self, actor_url: &str) -> Result<RemoteActor, Box<dyn Error>> {
+        if let Some(cached) = self.entries.read().await.get(actor_url) {
+            if cached.fetched_at.elapsed() < self.ttl {
+                return Ok(cached.actor.clone());
+            }
+        }
+
+        let doc: Value = self
+            .client
+            .get(actor_url)
+            .header(axum::http::header::ACCEPT, "application/activity+json")
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let inbox = doc["inbox"].as_str().ok_or("remote actor is missing an inbox")?.to_owned();
+        let shared_inbox = doc["endpoints"]["sharedInbox"].as_str().map(str::to_owned);
+        let public_key_pem = doc["publicKey"]["publicKeyPem"]
+            .as_str()
+            .ok_or("remote actor is missing a publicKey")?
+            .to_owned();
+
+        let actor = RemoteActor {
+            inbox,
+            shared_inbox,
+            public_key_pem,
+        };
+
+        self.entries.write().await.insert(
+            actor_url.to_owned(),
+            CachedActor {
+                actor: actor.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+
+        Ok(actor)
+    }
+}
+
+fn digest_header(body: &[u8]) -> String {
+    format!("SHA-256={}", base64::encode(Sha256::digest(body)))
+}
+
+fn http_date() -> String {
+    Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Sign `signing_string` with `private_key`, as required for the
+/// `Signature` header's `signature` field.
+fn sign(private_key: &RsaPrivateKey, signing_string: &str) -> Result<String, Box<dyn Error>> {
+    let hashed = Sha256::digest(signing_string.as_bytes());
+    let signature = private_key.sign(Pkcs1v15Sign::new::<Sha256>(), &hashed)?;
+    Ok(base64::encode(signature))
+}
+
+/// Generate a fresh RSA keypair for a subreddit's actor, PEM-encoded as
+/// `(private, public)`.
+fn generate_keypair() -> Result<(String, String), Box<dyn Error>> {
+    let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048)?;
+    let public_key = RsaPublicKey::from(&private_key);
+
+    let private_pem = private_key.to_pkcs8_pem(LineEnding::LF)?.to_string();
+    let public_pem = public_key.to_public_key_pem(LineEnding::LF)?;
+
+    Ok((private_pem, public_pem))
+}
+
+/// Load a subreddit's actor keypair, generating and persisting one the
+/// first time it's needed so the actor's identity stays stable across
+/// restarts.
+async fn get_or_create_keypair(
+    store: &dyn Store,
+    subreddit_id: i64,
+) -> Result<(RsaPrivateKey, String), HttpError> {
+    if let Some((private_pem, public_pem)) = store.actor_keypair(subreddit_id).await? {
+        let private_key =
+            RsaPrivateKey::from_pkcs8_pem(&private_pem).map_err(|e| HttpError::Other(Box::new(e)))?;
+        return Ok((private_key, public_pem));
+    }
+
+    let (private_pem, public_pem) = generate_keypair().map_err(HttpError::Other)?;
+    store.store_actor_keypair(subreddit_id, &private_pem, &public_pem).await?;
+
+    let private_key =
+        RsaPrivateKey::from_pkcs8_pem(&private_pem).map_err(|e| HttpError::Other(Box::new(e)))?;
+    Ok((private_key, public_pem))
+}
+
+/// POST `body` to `inbox`, signed with `private_key` under `key_id` per the
+/// `(request-target)`/host/date/digest HTTP Signature scheme.
+async fn deliver_signed(
+    client: &reqwest::Client,
+    private_key: &RsaPrivateKey,
+    key_id: &str,
+    inbox: &str,
+    body: Vec<u8>,
+) -> Result<(), Box<dyn Error>> {
+    let url = reqwest::Url::parse(inbox)?;
+    let host = url.host_str().ok_or("inbox URL has no host")?.to_owned();
+    let path = url.path().to_owned();
+    let date = http_date();
+    let digest = digest_header(&body);
+
+    let signing_string = format!(
+        "(request-target): post {}\nhost: {}\ndate: {}\ndigest: {}",
+        path, host, date, digest
+    );
+    let signature = sign(private_key, &signing_string)?;
+    let signature_header = format!(
+        "keyId=\"{}\",algorithm=\"rsa-sha256\",headers=\"(request-target) host date digest\",signature=\"{}\"",
+        key_id, signature
+    );
+
+    let res = client
+        .post(inbox)
+        .header("Host", host)
+        .header("Date", date)
+        .header("Digest", digest)
+        .header("Signature", signature_header)
+        .header(axum::http::header::CONTENT_TYPE, "application/activity+json")
+        .body(body)
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        return Err(format!("inbox rejected delivery with {}", res.status()).into());
+    }
+
+    Ok(())
+}
+
+struct SignatureHeader {
+    key_id: String,
+    headers: Vec<String>,
+    signature: Vec<u8>,
+}
+
+fn parse_signature_header(raw: &str) -> Option<SignatureHeader> {
+    let mut key_id = None;
+    let mut headers = None;
+    let mut signature = None;
+
+    for part in raw.split(',') {
+        let (k, v) = part.split_once('=')?;
+        let v = v.trim_matches('"');
+        match k {
+            "keyId" => key_id = Some(v.to_owned()),
+            "headers" => headers = Some(v.split(' ').map(str::to_owned).collect()),
+            "signature" => signature = Some(base64::decode(v).ok()?),
+            _ => {}
+        }
+    }
+
+    Some(SignatureHeader {
+        key_id: key_id?,
+        headers: headers.unwrap_or_else(|| vec!["date".to_owned()]),
+        signature: signature?,
+    })
+}
+
+/// Headers a `Signature` must cover for it to be trusted: without these, the
+/// signer is free to leave the request method/path or body unauthenticated
+/// while still presenting a technically-valid signature.
+const REQUIRED_SIGNED_HEADERS: [&str; 4] = ["(request-target)", "host", "date", "digest"];
+
+/// Verify an inbound request's `Signature` header by refetching the
+/// claimed actor's public key (via `actor_cache`) and checking it against
+/// the signing string its own `headers` list claims to cover. Also checks
+/// that the signer's own identity (`key_id`'s actor) matches
+/// `expected_actor`, so a validly-signed request can't claim to be from a
+/// different actor than the one that actually signed it.
+async fn verify_signature(
+    actor_cache: &ActorCache,
+    headers: &HeaderMap,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    expected_actor: &str,
+) -> bool {
+    let Some(raw) = headers.get("signature").and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let Some(sig) = parse_signature_header(raw) else {
+        return false;
+    };
+
+    if !REQUIRED_SIGNED_HEADERS.iter().all(|h| sig.headers.iter().any(|signed| signed == h)) {
+        return false;
+    }
+
+    let actor_url = sig.key_id.split('#').next().unwrap_or(&sig.key_id);
+    if actor_url != expected_actor {
+        return false;
+    }
+
+    let actor = match actor_cache.get(actor_url).await {
+        Ok(actor) => actor,
+        Err(e) => {
+            warn!(%actor_url, %e, "failed to fetch remote actor for signature verification");
+            return false;
+        }
+    };
+
+    let public_key = match RsaPublicKey::from_public_key_pem(&actor.public_key_pem) {
+        Ok(key) => key,
+        Err(e) => {
+            warn!(%actor_url, %e, "remote actor has an unparseable public key");
+            return false;
+        }
+    };
+
+    let mut lines = Vec::with_capacity(sig.headers.len());
+    for name in &sig.headers {
+        let line = if name == "(request-target)" {
+            format!("(request-target): {} {}", method.to_lowercase(), path)
+        } else if name == "digest" {
+            format!("digest: {}", digest_header(body))
+        } else {
+            match headers.get(name).and_then(|v| v.to_str().ok()) {
+                Some(value) => format!("{}: {}", name, value),
+                None => return false,
+            }
+        };
+        lines.push(line);
+    }
+    let signing_string = lines.join("\n");
+    let hashed = Sha256::digest(signing_string.as_bytes());
+
+    public_key
+        .verify(Pkcs1v15Sign::new::<Sha256>(), &hashed, &sig.signature)
+        .is_ok()
+}
+
+fn note_activity(actor_url: &str, entry: &FeedEntry) -> Value {
+    json!({
+        "id": format!("{}#note-{}", actor_url, entry.threshold_passed.timestamp()),
+        "type": "Create",
+        "actor": actor_url,
+        "to": ["https://www.w3.org/ns/activitystreams#Public"],
+        "object": {
+            "id": format!("{}#note-{}-object", actor_url, entry.threshold_passed.timestamp()),
+            "type": "Note",
+            "attributedTo": actor_url,
+            "content": format!("{} ({})", entry.title, entry.url),
+            "url": entry.url,
+            "published": entry.threshold_passed.to_rfc3339(),
+        },
+    })
+}
+
+/// Build and deliver a signed `Create(Note)` activity to every follower of
+/// `subreddit`'s actor, for a post that just passed the threshold. Mirrors
+/// [`crate::websub::distribute`]'s fan-out, but over ActivityPub.
+#[instrument(skip(store, entry))]
+pub async fn notify_followers(store: &dyn Store, base_url: &str, subreddit: &str, subreddit_id: i64, entry: &FeedEntry) {
+    let followers = match store.followers(subreddit_id).await {
+        Ok(followers) => followers,
+        Err(e) => {
+            error!(%subreddit, %e, "failed to load followers");
+            return;
+        }
+    };
+
+    if followers.is_empty() {
+        return;
+    }
+
+    let (private_key, _) = match get_or_create_keypair(store, subreddit_id).await {
+        Ok(keys) => keys,
+        Err(e) => {
+            error!(%subreddit, %e, "failed to load actor keypair");
+            return;
+        }
+    };
+
+    let actor_url = format!("{}/{}/actor", base_url, subreddit);
+    let key_id = format!("{}#main-key", actor_url);
+
+    let mut activity = note_activity(&actor_url, entry);
+    activity["@context"] = json!("https://www.w3.org/ns/activitystreams");
+    let body = serde_json::to_vec(&activity).expect("activity always serializes");
+
+    let client = &*CLIENT;
+    for follower in followers {
+        match deliver_signed(client, &private_key, &key_id, &follower.inbox, body.clone()).await {
+            Ok(()) => info!(actor = %follower.actor, %subreddit, "delivered Create(Note)"),
+            Err(e) => warn!(actor = %follower.actor, %subreddit, %e, "failed to deliver Create(Note)"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebfingerQuery {
+    resource: String,
+}
+
+/// `GET /.well-known/webfinger` -- resolves `acct:<subreddit>@<host>` to the
+/// subreddit's actor document, so a `@subreddit@host` handle is enough for a
+/// fediverse user to find and follow it.
+#[instrument(skip(store))]
+pub async fn webfinger_handler(
+    Extension(store): Extension<Arc<dyn Store>>,
+    Extension(BaseUrl(base_url)): Extension<BaseUrl>,
+    Query(query): Query<WebfingerQuery>,
+) -> Result<Json<Value>, HttpError> {
+    let subreddit = query
+        .resource
+        .strip_prefix("acct:")
+        .and_then(|rest| rest.split('@').next())
+        .ok_or(HttpError::NotFound)?;
+
+    if store.subreddit_id(subreddit).await?.is_none() {
+        return Err(HttpError::NotFound);
+    }
+
+    let actor_url = format!("{}/{}/actor", base_url, subreddit);
+    Ok(Json(json!({
+        "subject": query.resource,
+        "links": [{
+            "rel": "self",
+            "type": "application/activity+json",
+            "href": actor_url,
+        }],
+    })))
+}
+
+/// `GET /:subreddit/actor` -- the ActivityPub actor document for a
+/// subreddit's feed, generating its keypair on first request.
+#[instrument(skip(store))]
+pub async fn actor_handler(
+    Extension(store): Extension<Arc<dyn Store>>,
+    Extension(BaseUrl(base_url)): Extension<BaseUrl>,
+    Path(subreddit): Path<String>,
+) -> Result<Json<Value>, HttpError> {
+    let subreddit_id = store.subreddit_id(&subreddit).await?.ok_or(HttpError::NotFound)?;
+    let (_, public_key_pem) = get_or_create_keypair(store.as_ref(), subreddit_id).await?;
+
+    let actor_url = format!("{}/{}/actor", base_url, subreddit);
+    Ok(Json(json!({
+        "@context": ["https://www.w3.org/ns/activitystreams", "https://w3id.org/security/v1"],
+        "id": actor_url,
+        "type": "Person",
+        "preferredUsername": subreddit,
+        "inbox": format!("{}/{}/inbox", base_url, subreddit),
+        "outbox": format!("{}/{}/outbox", base_url, subreddit),
+        "publicKey": {
+            "id": format!("{}#main-key", actor_url),
+            "owner": actor_url,
+            "publicKeyPem": public_key_pem,
+        },
+    })))
+}
+
+/// `POST /:subreddit/inbox` -- accepts signed `Follow` activities and
+/// records the sender as a follower, replying with a signed `Accept`.
+/// Everything else is acknowledged and ignored.
+#[instrument(skip(store, actor_cache, headers, body))]
+pub async fn inbox_handler(
+    Extension(store): Extension<Arc<dyn Store>>,
+    Extension(actor_cache): Extension<Arc<ActorCache>>,
+    Extension(BaseUrl(base_url)): Extension<BaseUrl>,
+    Path(subreddit): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, HttpError> {
+    let activity: Value = serde_json::from_slice(&body).map_err(|e| HttpError::Other(Box::new(e)))?;
+    let actor_url = activity["actor"].as_str().ok_or(HttpError::Unauthorized)?.to_owned();
+
+    let path = format!("/{}/inbox", subreddit);
+    if !verify_signature(&actor_cache, &headers, "post", &path, &body, &actor_url).await {
+        warn!(%subreddit, %actor_url, "rejecting inbox request with an invalid, missing, or mismatched signature");
+        return Err(HttpError::Unauthorized);
+    }
+
+    let subreddit_id = store.subreddit_id(&subreddit).await?.ok_or(HttpError::NotFound)?;
+
+    let Some("Follow") = activity["type"].as_str() else {
+        debug!(%subreddit, activity_type = ?activity["type"], "ignoring unsupported inbox activity");
+        return Ok(StatusCode::ACCEPTED);
+    };
+
+    let remote = actor_cache.get(&actor_url).await.map_err(HttpError::Other)?;
+    store
+        .add_follower(subreddit_id, &actor_url, &remote.inbox, remote.shared_inbox.as_deref())
+        .await?;
+    info!(%subreddit, %actor_url, "accepted follow");
+
+    let (private_key, _) = get_or_create_keypair(store.as_ref(), subreddit_id).await?;
+    let subreddit_actor_url = format!("{}/{}/actor", base_url, subreddit);
+    let accept = json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{}#accepts-{}", subreddit_actor_url, Utc::now().timestamp()),
+        "type": "Accept",
+        "actor": subreddit_actor_url,
+        "object": activity,
+    });
+    let key_id = format!("{}#main-key", subreddit_actor_url);
+    let accept_body = serde_json::to_vec(&accept).expect("activity always serializes");
+    if let Err(e) = deliver_signed(&CLIENT, &private_key, &key_id, &remote.inbox, accept_body).await {
+        error!(%subreddit, %actor_url, %e, "failed to deliver Accept");
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Page size for [`outbox_handler`]'s `OrderedCollectionPage`s.
+const OUTBOX_PAGE_SIZE: i64 = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxQuery {
+    page: Option<i64>,
+}
+
+/// `GET /:subreddit/outbox` -- a subreddit's threshold-passing posts as
+/// paginated `Create(Note)` activities, so a follower's client can read back
+/// through its history the way it would any other actor's outbox.
+#[instrument(skip(store))]
+pub async fn outbox_handler(
+    Extension(store): Extension<Arc<dyn Store>>,
+    Extension(BaseUrl(base_url)): Extension<BaseUrl>,
+    Path(subreddit): Path<String>,
+    Query(query): Query<OutboxQuery>,
+) -> Result<Json<Value>, HttpError> {
+    if store.subreddit_id(&subreddit).await?.is_none() {
+        return Err(HttpError::NotFound);
+    }
+
+    let outbox_url = format!("{}/{}/outbox", base_url, subreddit);
+
+    let Some(page) = query.page else {
+        return Ok(Json(json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "id": outbox_url,
+            "type": "OrderedCollection",
+            "first": format!("{}?page=1", outbox_url),
+        })));
+    };
+
+    let actor_url = format!("{}/{}/actor", base_url, subreddit);
+    let offset = page.saturating_sub(1).max(0).saturating_mul(OUTBOX_PAGE_SIZE);
+    let entries = store.feed_entries_page(&subreddit, offset, OUTBOX_PAGE_SIZE).await?;
+
+    let items: Vec<Value> = entries.iter().map(|entry| note_activity(&actor_url, entry)).collect();
+
+    let mut page_doc = json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": format!("{}?page={}", outbox_url, page),
+        "type": "OrderedCollectionPage",
+        "partOf": outbox_url,
+        "orderedItems": items,
+    });
+
+    if entries.len() as i64 == OUTBOX_PAGE_SIZE {
+        page_doc["next"] = json!(format!("{}?page={}", outbox_url, page + 1));
+    }
+
+    Ok(Json(page_doc))
+}
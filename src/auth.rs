@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::header::AUTHORIZATION;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use crate::store::Store;
+use crate::HttpError;
+
+/// Number of random bytes in a newly minted token, hex-encoded to a
+/// 64-character string.
+const TOKEN_BYTES: usize = 32;
+
+/// Mint a new bearer token. Only [`hash_token`]'s digest of the result is
+/// ever persisted, so the caller must show this to the operator immediately
+/// -- it can't be recovered later.
+pub fn generate_token() -> String {
+    let bytes: [u8; TOKEN_BYTES] = rand::thread_rng().gen();
+    hex::encode(bytes)
+}
+
+/// Hash a token for storage and lookup. Tokens are already high-entropy
+/// random bytes rather than user-chosen secrets, so a plain SHA-256 digest
+/// is enough to keep the `tokens` table useless to an attacker, without the
+/// per-request cost a slow password hash like Argon2 would add here.
+pub fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Axum middleware that requires a valid `Authorization: Bearer <token>`
+/// header, checked against the `tokens` table, before letting a request
+/// through. Guards the private subreddit-creation API.
+pub async fn require_token(req: Request<Body>, next: Next<Body>) -> Response {
+    let Some(store) = req.extensions().get::<Arc<dyn Store>>().cloned() else {
+        error!("auth middleware ran without a Store extension");
+        return HttpError::Unauthorized.into_response();
+    };
+
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    let Some(token) = token else {
+        return HttpError::Unauthorized.into_response();
+    };
+
+    match store.token_is_valid(&hash_token(token)).await {
+        Ok(true) => next.run(req).await,
+        Ok(false) => HttpError::Unauthorized.into_response(),
+        Err(e) => {
+            error!(%e, "failed to validate token");
+            HttpError::Unauthorized.into_response()
+        }
+    }
+}
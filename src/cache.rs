@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use tracing::{debug, info, instrument};
+
+use crate::ts_float_seconds;
+
+/// Default interval after which a cached page is considered stale and
+/// refetched, either by a caller or by the rehydration task.
+pub const DEFAULT_REFETCH_AFTER: Duration = Duration::from_secs(30 * 60);
+
+/// Timeout applied to every Reddit page fetch, so a stalled upstream can't
+/// block a scrape job (and its retry/isolation guarantees) forever.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Post {
+    pub title: String,
+    pub ups: u32,
+    pub permalink: String,
+    pub url: String,
+    pub id: String,
+
+    #[serde(deserialize_with = "ts_float_seconds::deserialize")]
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ListingChild {
+    data: Post,
+    kind: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ListingData {
+    children: Vec<ListingChild>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Listing {
+    data: ListingData,
+}
+
+/// Whether [`RedditCache::get_page`] served its result from cache or had to
+/// hit Reddit.
+#[derive(Debug)]
+pub enum MaybeCached<T> {
+    Cached(T),
+    Fresh(T),
+}
+
+impl<T> MaybeCached<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            MaybeCached::Cached(v) => v,
+            MaybeCached::Fresh(v) => v,
+        }
+    }
+}
+
+type CacheKey = (String, Option<String>);
+
+struct Entry {
+    value: Vec<(String, Post)>,
+    fetched_at: Instant,
+}
+
+#[instrument(skip(client))]
+async fn fetch_page(
+    client: &reqwest::Client,
+    subreddit: &str,
+    after: Option<String>,
+) -> Result<Vec<(String, Post)>, Box<dyn Error>> {
+    let url = format!("https://reddit.com/r/{}/new.json", subreddit);
+    let mut query: Vec<(&str, String)> = vec![];
+
+    if let Some(after) = after {
+        query.push(("after", after));
+    }
+
+    let query_string = query
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join(",");
+    info!(%subreddit, %url, %query_string, "sending request");
+
+    let timer = crate::metrics::METRICS.page_fetch_seconds.start_timer();
+    let res = client.get(url).query(&query).send().await;
+    timer.observe_duration();
+    crate::metrics::METRICS.pages_fetched.inc();
+    debug!(%subreddit, ?res, "got result");
+
+    let res: Listing = res?.json().await?;
+    debug!(%subreddit, ?res, "parsed");
+
+    Ok(res
+        .data
+        .children
+        .into_iter()
+        .map(|child| (child.kind, child.data))
+        .collect())
+}
+
+/// A TTL cache in front of Reddit's listing endpoint, keyed by
+/// `(subreddit, after)`, so that many subreddits (or repeated worker cycles)
+/// don't duplicate requests and risk rate-limiting. One `reqwest::Client` is
+/// shared across every fetch.
+pub struct RedditCache {
+    client: reqwest::Client,
+    entries: RwLock<HashMap<CacheKey, Entry>>,
+    refetch_after: Duration,
+}
+
+impl RedditCache {
+    pub fn new(refetch_after: Duration) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(HTTP_TIMEOUT)
+                .build()
+                .expect("reqwest client with a timeout can always be built"),
+            entries: RwLock::new(HashMap::new()),
+            refetch_after,
+        }
+    }
+
+    /// Fetch a page of `subreddit`'s new posts starting `after`, serving a
+    /// cached copy if it's younger than `refetch_after`.
+    #[instrument(skip(self))]
+    pub async fn get_page(
+        &self,
+        subreddit: &str,
+        after: Option<String>,
+    ) -> Result<MaybeCached<Vec<(String, Post)>>, Box<dyn Error>> {
+        let key = (subreddit.to_owned(), after.clone());
+
+        if let Some(entry) = self.entries.read().await.get(&key) {
+            if entry.fetched_at.elapsed() < self.refetch_after {
+                debug!(%subreddit, ?after, "serving cached page");
+                return Ok(MaybeCached::Cached(entry.value.clone()));
+            }
+        }
+
+        let value = fetch_page(&self.client, subreddit, after).await?;
+        self.entries.write().await.insert(
+            key,
+            Entry {
+                value: value.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+
+        Ok(MaybeCached::Fresh(value))
+    }
+
+    /// Proactively refetch the `after=None` head of each subreddit (the only
+    /// key a scrape cycle ever re-requests) once its TTL is within `margin`
+    /// of expiring, so callers rarely block on a cold fetch. Entries for a
+    /// historical `after` cursor are visited at most once per pagination run
+    /// and would otherwise accumulate and get refetched forever, so those are
+    /// evicted once expired instead of rehydrated.
+    async fn rehydrate(&self, margin: Duration) {
+        let (heads, stale_pages): (Vec<CacheKey>, Vec<CacheKey>) = {
+            let entries = self.entries.read().await;
+            let heads = entries
+                .iter()
+                .filter(|(key, entry)| key.1.is_none() && entry.fetched_at.elapsed() + margin >= self.refetch_after)
+                .map(|(key, _)| key.clone())
+                .collect();
+            let stale_pages = entries
+                .iter()
+                .filter(|(key, entry)| key.1.is_some() && entry.fetched_at.elapsed() >= self.refetch_after)
+                .map(|(key, _)| key.clone())
+                .collect();
+            (heads, stale_pages)
+        };
+
+        if !stale_pages.is_empty() {
+            let mut entries = self.entries.write().await;
+            for key in &stale_pages {
+                entries.remove(key);
+            }
+            debug!(count = stale_pages.len(), "evicted expired paginated cache entries");
+        }
+
+        for (subreddit, after) in heads {
+            debug!(%subreddit, ?after, "rehydrating stale cache entry");
+            match fetch_page(&self.client, &subreddit, after.clone()).await {
+                Ok(value) => {
+                    self.entries.write().await.insert(
+                        (subreddit, after),
+                        Entry {
+                            value,
+                            fetched_at: Instant::now(),
+                        },
+                    );
+                }
+                Err(e) => tracing::error!(%subreddit, %e, "failed to rehydrate cache entry"),
+            }
+        }
+    }
+}
+
+/// Spawn a background task that periodically calls [`RedditCache::rehydrate`]
+/// so entries get refreshed before a worker would otherwise block on them.
+pub fn spawn_rehydration(cache: Arc<RedditCache>, check_every: Duration, margin: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(check_every).await;
+            cache.rehydrate(margin).await;
+        }
+    });
+}
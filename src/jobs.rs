@@ -0,0 +1,50 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of attempts before a scrape job is abandoned and marked
+/// failed instead of being retried.
+pub const MAX_ATTEMPTS: i32 = 8;
+
+/// Base delay used to compute the exponential backoff between retries.
+const BASE_BACKOFF_SECONDS: i64 = 30;
+
+/// Upper bound on how long a retry can be delayed, so a long string of
+/// failures doesn't push a job's next attempt out indefinitely.
+const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+
+/// How long after a successful scrape the subreddit is rescheduled.
+pub const SCRAPE_INTERVAL_SECONDS: i64 = 5 * 60;
+
+/// How long a job can sit in the `running` state before it's considered
+/// abandoned (its worker crashed or was killed without ever completing,
+/// retrying or failing it) and swept back to `pending`/`failed`.
+pub const STALE_RUNNING_SECONDS: i64 = 20 * 60;
+
+/// The delay to apply before retrying a job whose stored attempt count is
+/// `attempts`, not counting the failure that triggered this retry: the first
+/// retry waits `BASE_BACKOFF_SECONDS`, and each later one twice as long.
+pub fn backoff(attempts: i32) -> Duration {
+    let secs = BASE_BACKOFF_SECONDS.saturating_mul(1i64 << attempts.clamp(0, 20));
+    Duration::seconds(secs.min(MAX_BACKOFF_SECONDS))
+}
+
+/// The payload of a scrape job: `rexml` only has one kind of job today, so
+/// this is encoded directly as a job's `payload` column rather than adding a
+/// `kind` discriminator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapePayload {
+    pub subreddit: String,
+    pub subreddit_id: i64,
+    pub cutoff_seconds: i64,
+    pub threshold: u32,
+}
+
+impl ScrapePayload {
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("ScrapePayload always serializes")
+    }
+
+    pub fn decode(payload: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(payload)
+    }
+}
@@ -6,6 +6,14 @@ use axum::{
 };
 use tracing::{error, debug};
 
+pub mod activitypub;
+pub mod auth;
+pub mod cache;
+pub mod jobs;
+pub mod metrics;
+pub mod store;
+pub mod websub;
+
 pub mod ts_float_seconds {
     use chrono::{DateTime, NaiveDateTime, Utc};
     use serde::de;
@@ -45,6 +53,9 @@ pub enum HttpError {
     #[error("already exists")]
     AlreadyExists,
 
+    #[error("unauthorized")]
+    Unauthorized,
+
     #[error("a database error occurred")]
     Sqlx(#[from] sqlx::Error),
 
@@ -59,6 +70,7 @@ impl IntoResponse for HttpError {
         match self {
             HttpError::NotFound => (StatusCode::NOT_FOUND, msg).into_response(),
             HttpError::AlreadyExists => (StatusCode::CONFLICT, msg).into_response(),
+            HttpError::Unauthorized => (StatusCode::UNAUTHORIZED, msg).into_response(),
             HttpError::Sqlx(_) | HttpError::Other(_) => {
                 error!(%self, "internal server error");
                 debug!(?self, "internal server error");
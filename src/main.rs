@@ -5,13 +5,15 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use chrono::{DateTime, NaiveDateTime, Utc};
-use futures::TryStreamExt;
+use chrono::{DateTime, Utc};
 use minidom::Element;
-use rexml::{ts_float_seconds, HttpError};
+use rexml::activitypub::{ActorCache, BaseUrl};
+use rexml::cache::{MaybeCached, RedditCache, DEFAULT_REFETCH_AFTER};
+use rexml::store::{FeedEntry, Job, Store};
+use rexml::{activitypub, jobs, websub, HttpError};
 use serde::Deserialize;
-use sqlx::{query, sqlite::SqlitePoolOptions, SqlitePool};
 use std::error::Error;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
@@ -19,72 +21,12 @@ use tracing::{debug, error, info, instrument};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Registry};
 use tracing_tree::HierarchicalLayer;
 
-#[derive(Debug, Clone, Deserialize)]
-struct Post {
-    title: String,
-    ups: u32,
-    permalink: String,
-    url: String,
-    id: String,
+const BASE_URL: &str = "http://rexml.mattjhall.xyz";
 
-    #[serde(deserialize_with = "ts_float_seconds::deserialize")]
-    created: DateTime<Utc>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct ListingChild {
-    data: Post,
-    kind: String,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct ListingData {
-    children: Vec<ListingChild>,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-struct Listing {
-    data: ListingData,
-}
-
-#[instrument]
-async fn get_page(
-    subreddit: &str,
-    after: Option<String>,
-) -> Result<Vec<(String, Post)>, Box<dyn Error>> {
-    let client = reqwest::Client::new();
-
-    let url = format!("https://reddit.com/r/{}/new.json", subreddit);
-    let mut query: Vec<(&str, String)> = vec![];
-
-    if let Some(after) = after {
-        query.push(("after", after));
-    }
-
-    let query_string = query
-        .iter()
-        .map(|(k, v)| format!("{}={}", k, v))
-        .collect::<Vec<String>>()
-        .join(",");
-    info!(%subreddit, %url, %query_string, "sending request");
-
-    let res = client.get(url).query(&query).send().await;
-    debug!(%subreddit, ?res, "got result");
-
-    let res: Listing = res?.json().await?;
-    debug!(%subreddit, ?res, "parsed");
-
-    Ok(res
-        .data
-        .children
-        .into_iter()
-        .map(|child| (child.kind, child.data))
-        .collect())
-}
-
-#[instrument]
+#[instrument(skip(store, cache))]
 async fn get_subreddit_results(
-    pool: &SqlitePool,
+    store: Arc<dyn Store>,
+    cache: &RedditCache,
     subreddit: String,
     subreddit_id: i64,
     cutoff: chrono::Duration,
@@ -92,51 +34,79 @@ async fn get_subreddit_results(
 ) -> Result<(), Box<dyn Error>> {
     info!(%subreddit, "scraping");
 
+    // Observed on drop, so every exit path (including early returns via `?`)
+    // records the scrape's duration.
+    let _scrape_timer = rexml::metrics::METRICS
+        .scrape_duration_seconds
+        .with_label_values(&[&subreddit])
+        .start_timer();
+
     let mut after: Option<String> = None;
 
     'a: loop {
-        let mut res = get_page(&subreddit, after).await?;
-        info!(%subreddit, "got {} results", res.len());
+        let cached = cache.get_page(&subreddit, after).await?;
+        let from_cache = matches!(cached, MaybeCached::Cached(_));
+        let mut res = cached.into_inner();
+        info!(%subreddit, %from_cache, "got {} results", res.len());
         if res.is_empty() {
             break;
         }
 
-        {
-            let mut conn = pool.acquire().await?;
-            for (kind, post) in &res {
-                debug!(%subreddit, "({}) {} - {}", post.ups, post.title, post.url);
-                if post.created < Utc::now() - cutoff {
-                    break 'a;
-                }
-
-                let ups = sqlx::query!("SELECT ups FROM posts WHERE reddit_id=?", post.id)
-                    .fetch_optional(&mut conn)
-                    .await?;
+        for (kind, post) in &res {
+            debug!(%subreddit, "({}) {} - {}", post.ups, post.title, post.url);
+            if post.created < Utc::now() - cutoff {
+                break 'a;
+            }
 
-                if ups.is_none() {
-                    let created = post.created.timestamp();
+            let existing = store
+                .upsert_post(
+                    subreddit_id,
+                    &post.id,
+                    kind,
+                    &post.title,
+                    &post.url,
+                    &post.permalink,
+                    post.created,
+                    post.ups,
+                )
+                .await?;
+
+            if existing.is_none() {
+                rexml::metrics::METRICS.posts_inserted.inc();
+            }
 
-                    sqlx::query!(
-                        "INSERT INTO posts(reddit_id, subreddit, kind, title, url, permalink, created, ups)
-                         VALUES (?,?,?,?,?,?,?,?)",
-                         post.id, subreddit_id, kind, post.title, post.url, post.permalink, created, post.ups
-                    ).execute(&mut conn).await?;
-                }
+            if post.ups >= threshold && existing.map_or(true, |e| e.ups < threshold) {
+                info!(%subreddit, %post.id, "passed the threshold");
 
-                if post.ups >= threshold && (ups.is_none() || (ups.unwrap().ups as u32) < threshold)
-                {
-                    info!(%subreddit, %post.id, "passed the threshold");
-
-                    let now_timestamp = Utc::now().timestamp();
-                    sqlx::query!(
-                        "UPDATE posts SET ups = ?, threshold_passed = ? WHERE reddit_id = ? AND subreddit = ?",
-                        post.ups,
-                        now_timestamp,
-                        post.id,
-                        subreddit_id,
-                    )
-                    .execute(&mut conn)
+                store
+                    .mark_threshold_passed(subreddit_id, &post.id, post.ups, Utc::now())
                     .await?;
+
+                rexml::metrics::METRICS
+                    .posts_passed_threshold
+                    .with_label_values(&[&subreddit])
+                    .inc();
+
+                let topic = format!("{}/{}", BASE_URL, subreddit);
+                let entries = store.feed_entries(&subreddit, 50).await?;
+                let body = build_feed(&subreddit, &entries)?;
+
+                // Spawned as detached tasks rather than awaited here: each
+                // subscriber/follower delivery can take up to the client's
+                // timeout, and awaiting them inline would tie up this scrape
+                // job's slot on nothing but third-party network I/O.
+                let distribute_store = store.clone();
+                tokio::spawn(async move {
+                    websub::distribute(distribute_store.as_ref(), &topic, body).await;
+                });
+
+                if let Some(entry) = entries.first().cloned() {
+                    let notify_store = store.clone();
+                    let subreddit = subreddit.clone();
+                    tokio::spawn(async move {
+                        activitypub::notify_followers(notify_store.as_ref(), BASE_URL, &subreddit, subreddit_id, &entry)
+                            .await;
+                    });
                 }
             }
         }
@@ -148,113 +118,156 @@ async fn get_subreddit_results(
     Ok(())
 }
 
-#[instrument]
-async fn posts_worker(
-    pool: &SqlitePool,
-    mut rx: mpsc::Receiver<bool>,
-) -> Result<(), Box<dyn Error>> {
-    loop {
-        info!("scraping posts");
-
-        let futs = {
-            let mut conn = pool.acquire().await?;
-            let mut rows =
-                query!("SELECT id, name, time_cutoff_seconds, upvote_threshold FROM subreddits")
-                    .fetch(&mut conn);
-            let mut futs = Vec::new();
-            while let Some(row) = rows.try_next().await? {
-                debug!(?row, "got subreddit");
-
-                let dur = chrono::Duration::seconds(row.time_cutoff_seconds);
-                futs.push(get_subreddit_results(
-                    pool,
-                    row.name,
-                    row.id,
-                    dur,
-                    row.upvote_threshold as u32,
-                ));
+/// Run a single claimed scrape job to completion, then either reschedule it
+/// for its next periodic run, retry it with backoff, or give up, so one slow
+/// or failing subreddit can never delay or drop another's schedule.
+#[instrument(skip(store, cache, job), fields(job_id = job.id))]
+async fn run_scrape_job(store: Arc<dyn Store>, cache: &RedditCache, job: Job) {
+    let payload = match jobs::ScrapePayload::decode(&job.payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!(%e, "malformed job payload, dropping");
+            if let Err(e) = store.fail_job(job.id).await {
+                error!(%e, "failed to mark malformed job as failed");
             }
-            futs
-        };
+            return;
+        }
+    };
 
-        let results = futures::future::join_all(futs).await;
-        for res in results {
-            match res {
-                Ok(()) => {}
-                Err(e) => error!("error whilst getting results: {}", e),
+    let cutoff = chrono::Duration::seconds(payload.cutoff_seconds);
+    let res = get_subreddit_results(
+        store.clone(),
+        cache,
+        payload.subreddit.clone(),
+        payload.subreddit_id,
+        cutoff,
+        payload.threshold,
+    )
+    .await;
+
+    match res {
+        Ok(()) => {
+            if let Err(e) = store.complete_job(job.id).await {
+                error!(%e, "failed to complete job");
+                return;
             }
-        }
 
-        info!("waiting to scrape posts");
-        tokio::select! {
-            _ = tokio::time::sleep(std::time::Duration::from_secs(5 * 60)) => { info!("posts worker finished sleep"); },
-            _ = rx.recv() => { info!("posts worker woken up"); },
+            let next_run_at =
+                Utc::now() + chrono::Duration::seconds(jobs::SCRAPE_INTERVAL_SECONDS);
+            if let Err(e) = store.enqueue_job(&job.payload, next_run_at).await {
+                error!(%e, "failed to reschedule scrape job");
+            }
+        }
+        Err(e) => {
+            error!(subreddit = %payload.subreddit, %e, "scrape failed");
+
+            let attempts = job.attempts + 1;
+            let result = if attempts >= jobs::MAX_ATTEMPTS {
+                store.fail_job(job.id).await
+            } else {
+                store.retry_job(job.id, Utc::now() + jobs::backoff(job.attempts)).await
+            };
+
+            if let Err(e) = result {
+                error!(%e, "failed to update job after scrape failure");
+            }
         }
     }
 }
 
-fn timestamp_to_utc(ts: i64) -> DateTime<Utc> {
-    DateTime::from_utc(NaiveDateTime::from_timestamp(ts, 0), Utc)
+fn spawn_scrape_job(store: Arc<dyn Store>, cache: Arc<RedditCache>, job: Job) {
+    tokio::spawn(async move {
+        run_scrape_job(store, cache.as_ref(), job).await;
+    });
 }
 
-async fn handler(
-    Extension(State { pool, .. }): Extension<State>,
-    Path(subreddit): Path<String>,
-) -> Result<impl IntoResponse, HttpError> {
-    debug!(%subreddit, "got request");
-    let mut conn = pool.acquire().await?;
+async fn enqueue_scrape_job(
+    store: &dyn Store,
+    subreddit: &str,
+    run_at: DateTime<Utc>,
+) -> Result<(), Box<dyn Error>> {
+    let Some(row) = store
+        .list_subreddits()
+        .await?
+        .into_iter()
+        .find(|row| row.name == subreddit)
+    else {
+        return Ok(());
+    };
 
-    let res = sqlx::query!(
-        "SELECT id FROM subreddits WHERE subreddits.name = ? LIMIT 1",
-        subreddit
-    )
-    .fetch_one(&mut conn)
-    .await;
-    match res {
-        Ok(_) => {}
-        Err(sqlx::Error::RowNotFound) => {
-            debug!(%subreddit, "subreddit not registered");
-            return Err(HttpError::NotFound);
-        }
-        Err(e) => return Err(e.into()),
+    let payload = jobs::ScrapePayload {
+        subreddit: row.name,
+        subreddit_id: row.id,
+        cutoff_seconds: row.time_cutoff_seconds,
+        threshold: row.upvote_threshold as u32,
+    };
+    store.enqueue_job(&payload.encode(), run_at).await?;
+
+    Ok(())
+}
+
+/// Poll the database-backed job queue for due scrape jobs and run each as an
+/// independent task, rather than scraping every subreddit together on one
+/// fixed interval.
+#[instrument(skip(store, cache))]
+async fn jobs_worker(
+    store: Arc<dyn Store>,
+    cache: Arc<RedditCache>,
+    mut rx: mpsc::Receiver<String>,
+) -> Result<(), Box<dyn Error>> {
+    for row in store.list_subreddits().await? {
+        debug!(?row, "scheduling initial scrape");
+        let payload = jobs::ScrapePayload {
+            subreddit: row.name,
+            subreddit_id: row.id,
+            cutoff_seconds: row.time_cutoff_seconds,
+            threshold: row.upvote_threshold as u32,
+        };
+        // `_if_absent`, not `enqueue_job`: on every restart this loop runs
+        // again for every configured subreddit, and a plain enqueue would
+        // duplicate the backlog each time instead of resuming it.
+        store.enqueue_job_if_absent(&payload.encode(), Utc::now()).await?;
     }
 
-    let rows = sqlx::query!(
-        "SELECT p.title, p.url, p.threshold_passed
-          FROM subreddits s
-          LEFT JOIN posts p ON p.subreddit = s.id
-          WHERE s.name = ? AND p.threshold_passed IS NOT NULL
-          ORDER BY p.threshold_passed DESC
-          LIMIT 50",
-        subreddit
-    )
-    .fetch_all(&mut conn)
-    .await?;
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(2)) => {},
+            Some(subreddit) = rx.recv() => {
+                info!(%subreddit, "enqueuing immediate scrape for newly created subreddit");
+                if let Err(e) = enqueue_scrape_job(store.as_ref(), &subreddit, Utc::now()).await {
+                    error!(%subreddit, %e, "failed to enqueue scrape job");
+                }
+            }
+        }
 
-    let n_results = rows.len();
-    debug!(%n_results, "got posts");
+        let stale_before = Utc::now() - chrono::Duration::seconds(jobs::STALE_RUNNING_SECONDS);
+        if let Err(e) = store.reclaim_stale_jobs(stale_before, jobs::MAX_ATTEMPTS).await {
+            error!(%e, "failed to sweep stale running jobs");
+        }
+
+        while let Some(job) = store.claim_due_job(Utc::now()).await? {
+            spawn_scrape_job(store.clone(), cache.clone(), job);
+        }
+    }
+}
+
+/// Build the Atom document served at `/:subreddit` and pushed to WebSub
+/// subscribers when it changes.
+fn build_feed(subreddit: &str, entries: &[FeedEntry]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let topic = format!("{}/{}", BASE_URL, subreddit);
 
-    let entries = rows.iter().map(|row| {
-        let updated = timestamp_to_utc(row.threshold_passed.unwrap());
+    let atom_entries = entries.iter().map(|row| {
         Element::builder("entry", "")
-            .append(
-                Element::builder("id", "")
-                    .append(row.url.clone().unwrap())
-                    .build(),
-            )
-            .append(
-                Element::builder("title", "")
-                    .append(row.title.clone().unwrap())
-                    .build(),
-            )
+            .append(Element::builder("id", "").append(row.url.clone()).build())
+            .append(Element::builder("title", "").append(row.title.clone()).build())
             .append(
                 Element::builder("link", "")
-                    .attr("href", row.url.clone().unwrap())
+                    .attr("href", row.url.clone())
                     .build(),
             )
             .append(
                 Element::builder("updated", "")
-                    .append(updated.to_rfc3339())
+                    .append(row.threshold_passed.to_rfc3339())
                     .build(),
             )
             .build()
@@ -262,15 +275,17 @@ async fn handler(
 
     let mut feed = Element::builder("feed", "")
         .attr("xmlns", "http://www.w3.org/2005/Atom")
+        .append(Element::builder("id", "").append(topic.clone()).build())
         .append(
-            Element::builder("id", "")
-                .append(format!("http://rexml.mattjhall.xyz/{}", subreddit))
+            Element::builder("link", "")
+                .attr("rel", "self")
+                .attr("href", topic)
                 .build(),
         )
         .append(
             Element::builder("link", "")
-                .attr("rel", "self")
-                .attr("href", format!("http://rexml.mattjhall.xyz/{}", subreddit))
+                .attr("rel", "hub")
+                .attr("href", format!("{}/hub", BASE_URL))
                 .build(),
         )
         .append(
@@ -278,24 +293,41 @@ async fn handler(
                 .append(format!("{} posts", subreddit))
                 .build(),
         )
-        .append_all(entries);
+        .append_all(atom_entries);
 
-    if !rows.is_empty() {
+    if !entries.is_empty() {
         feed = feed.append(
             Element::builder("updated", "")
-                .append(timestamp_to_utc(rows[0].threshold_passed.unwrap()).to_rfc3339())
+                .append(entries[0].threshold_passed.to_rfc3339())
                 .build(),
         )
     }
 
-    let feed = feed.build();
-
     let mut res = Vec::new();
-    feed.write_to(&mut res)
-        .map_err(|e| Box::new(e) as Box<dyn Error>)?;
+    feed.build().write_to(&mut res)?;
+    Ok(res)
+}
+
+async fn handler(
+    Extension(State { store, .. }): Extension<State>,
+    Path(subreddit): Path<String>,
+) -> Result<impl IntoResponse, HttpError> {
+    debug!(%subreddit, "got request");
+
+    if store.subreddit_id(&subreddit).await?.is_none() {
+        debug!(%subreddit, "subreddit not registered");
+        return Err(HttpError::NotFound);
+    }
+
+    let rows = store.feed_entries(&subreddit, 50).await?;
+
+    let n_results = rows.len();
+    debug!(%n_results, "got posts");
+
+    let body = build_feed(&subreddit, &rows).map_err(HttpError::Other)?;
     let resp = Response::builder()
         .header(CONTENT_TYPE, "application/atom+xml")
-        .body(axum::body::Body::from(res))
+        .body(axum::body::Body::from(body))
         .map_err(|e| Box::new(e) as Box<dyn Error>)?;
     Ok(resp)
 }
@@ -307,45 +339,48 @@ struct CreateSubreddit {
 }
 
 async fn post_handler(
-    Extension(State { pool, tx }): Extension<State>,
+    Extension(State { store, tx }): Extension<State>,
     Path(subreddit): Path<String>,
     Json(payload): Json<CreateSubreddit>,
 ) -> Result<impl IntoResponse, HttpError> {
     info!(%subreddit, ?payload, "trying to create subreddit record");
-    let mut conn = pool.acquire().await?;
-    let res = query!(
-        "INSERT INTO subreddits(name, upvote_threshold, time_cutoff_seconds) VALUES (?,?,?)",
-        subreddit,
-        payload.upvote_threshold,
-        payload.time_cutoff_seconds
-    )
-    .execute(&mut conn)
-    .await;
-
-    let e = match res {
-        Ok(_) => {
-            let _ = tx.send(true).await;
-            return Ok(());
-        }
-        Err(sqlx::Error::Database(e)) => {
-            if let Some(code) = e.code() {
-                if code == "2067" {
-                    return Err(HttpError::AlreadyExists);
-                }
-            }
 
-            sqlx::Error::Database(e).into()
-        }
-        Err(e) => e.into(),
-    };
+    store
+        .create_subreddit(&subreddit, payload.upvote_threshold, payload.time_cutoff_seconds)
+        .await?;
 
-    Err(e)
+    let _ = tx.send(subreddit).await;
+    Ok(())
 }
 
 #[derive(Clone)]
 struct State {
-    pool: SqlitePool,
-    tx: mpsc::Sender<bool>,
+    store: Arc<dyn Store>,
+    tx: mpsc::Sender<String>,
+}
+
+/// `rexml mint-token <label>` -- mint a new bearer token for the private
+/// subreddit-creation API and print it once, since only its hash is kept.
+async fn mint_token(label: &str) -> Result<(), Box<dyn Error>> {
+    let conn_str = std::env::var("REXML_DB_URL").unwrap_or("sqlite://rexml.db".into());
+    let store = rexml::store::connect(&conn_str).await?;
+
+    let token = rexml::auth::generate_token();
+    store.create_token(label, &rexml::auth::hash_token(&token), Utc::now()).await?;
+
+    println!("minted token '{}': {}", label, token);
+    println!("store it now -- it cannot be recovered, only revoked");
+    Ok(())
+}
+
+/// `rexml revoke-token <label>` -- revoke a previously minted token.
+async fn revoke_token(label: &str) -> Result<(), Box<dyn Error>> {
+    let conn_str = std::env::var("REXML_DB_URL").unwrap_or("sqlite://rexml.db".into());
+    let store = rexml::store::connect(&conn_str).await?;
+
+    store.revoke_token(label).await?;
+    println!("revoked token '{}'", label);
+    Ok(())
 }
 
 #[tokio::main]
@@ -359,32 +394,58 @@ async fn main() -> Result<(), Box<dyn Error>> {
         )
         .init();
 
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("mint-token") => {
+            let label = args.next().ok_or("usage: rexml mint-token <label>")?;
+            return mint_token(&label).await;
+        }
+        Some("revoke-token") => {
+            let label = args.next().ok_or("usage: rexml revoke-token <label>")?;
+            return revoke_token(&label).await;
+        }
+        Some(other) => return Err(format!("unrecognised subcommand: {}", other).into()),
+        None => {}
+    }
+
     let conn_str = std::env::var("REXML_DB_URL").unwrap_or("sqlite://rexml.db".into());
     info!(%conn_str, "connecting");
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(1)
-        .connect(&conn_str)
-        .await?;
+    let store = rexml::store::connect(&conn_str).await?;
 
-    {
-        let mut conn = pool.acquire().await?;
-        sqlx::migrate!().run(&mut conn).await?;
-    }
+    let cache = Arc::new(RedditCache::new(DEFAULT_REFETCH_AFTER));
+    rexml::cache::spawn_rehydration(
+        cache.clone(),
+        std::time::Duration::from_secs(60),
+        std::time::Duration::from_secs(5 * 60),
+    );
 
     let (tx, rx) = mpsc::channel(1);
 
     let state = State {
-        pool: pool.clone(),
+        store: store.clone(),
         tx,
     };
 
+    let base_url = BaseUrl(Arc::from(BASE_URL));
+    let actor_cache = Arc::new(ActorCache::new(std::time::Duration::from_secs(60 * 60)));
+
     let app = Router::new()
         .route("/:subreddit", get(handler))
+        .route("/:subreddit/actor", get(activitypub::actor_handler))
+        .route("/:subreddit/inbox", post(activitypub::inbox_handler))
+        .route("/:subreddit/outbox", get(activitypub::outbox_handler))
+        .route("/.well-known/webfinger", get(activitypub::webfinger_handler))
+        .route("/hub", post(websub::hub_handler))
+        .route("/metrics", get(rexml::metrics::metrics_handler))
         .layer(Extension(state.clone()))
+        .layer(Extension(store.clone()))
+        .layer(Extension(base_url))
+        .layer(Extension(actor_cache))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
+                .layer(axum::middleware::from_fn(rexml::metrics::track_requests))
                 .into_inner(),
         );
 
@@ -394,8 +455,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
+                .layer(axum::middleware::from_fn(rexml::metrics::track_requests))
+                .layer(axum::middleware::from_fn(rexml::auth::require_token))
                 .into_inner(),
-        );
+        )
+        // Layered last so it runs first: `require_token` reads the store
+        // from the request's extensions.
+        .layer(Extension(store.clone()));
 
     let server =
         axum::Server::bind(&"0.0.0.0:4328".parse().unwrap()).serve(app.into_make_service());
@@ -403,7 +469,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let priv_server =
         axum::Server::bind(&"0.0.0.0:4329".parse().unwrap()).serve(priv_app.into_make_service());
 
-    let worker = posts_worker(&pool, rx);
+    let worker = jobs_worker(store.clone(), cache.clone(), rx);
 
     let _ = futures::join!(server, priv_server, worker);
 
@@ -0,0 +1,127 @@
+use axum::body::Body;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, Encoder, Histogram, HistogramVec, IntCounter,
+    IntCounterVec, Registry, TextEncoder,
+};
+
+/// Every Prometheus metric `rexml` exposes at `/metrics`, plus the registry
+/// they're collected into.
+pub struct Metrics {
+    registry: Registry,
+    pub pages_fetched: IntCounter,
+    pub posts_inserted: IntCounter,
+    pub posts_passed_threshold: IntCounterVec,
+    pub http_requests: IntCounterVec,
+    pub page_fetch_seconds: Histogram,
+    pub scrape_duration_seconds: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let pages_fetched = register_int_counter_with_registry!(
+            "rexml_reddit_pages_fetched_total",
+            "Number of Reddit listing pages fetched",
+            registry
+        )
+        .unwrap();
+
+        let posts_inserted = register_int_counter_with_registry!(
+            "rexml_posts_inserted_total",
+            "Number of posts newly seen and inserted",
+            registry
+        )
+        .unwrap();
+
+        let posts_passed_threshold = register_int_counter_vec_with_registry!(
+            "rexml_posts_passed_threshold_total",
+            "Number of posts that have crossed their subreddit's upvote threshold",
+            &["subreddit"],
+            registry
+        )
+        .unwrap();
+
+        let http_requests = register_int_counter_vec_with_registry!(
+            "rexml_http_requests_total",
+            "HTTP requests handled, by route and status",
+            &["method", "route", "status"],
+            registry
+        )
+        .unwrap();
+
+        let page_fetch_seconds = prometheus::Histogram::with_opts(prometheus::HistogramOpts::new(
+            "rexml_reddit_page_fetch_seconds",
+            "Latency of a single Reddit listing page fetch",
+        ))
+        .unwrap();
+        registry.register(Box::new(page_fetch_seconds.clone())).unwrap();
+
+        let scrape_duration_seconds = register_histogram_vec_with_registry!(
+            "rexml_scrape_duration_seconds",
+            "End-to-end duration of a subreddit scrape",
+            &["subreddit"],
+            registry
+        )
+        .unwrap();
+
+        Self {
+            registry,
+            pages_fetched,
+            posts_inserted,
+            posts_passed_threshold,
+            http_requests,
+            page_fetch_seconds,
+            scrape_duration_seconds,
+        }
+    }
+}
+
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// `GET /metrics` — encodes every collected metric in Prometheus text
+/// format.
+pub async fn metrics_handler() -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+
+    if let Err(e) = encoder.encode(&METRICS.registry.gather(), &mut buffer) {
+        tracing::error!(%e, "failed to encode metrics");
+        return Response::builder()
+            .status(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    Response::builder()
+        .header(axum::http::header::CONTENT_TYPE, encoder.format_type())
+        .body(Body::from(buffer))
+        .unwrap()
+}
+
+/// Axum middleware that counts every request by method, route and response
+/// status, for the `rexml_http_requests_total` counter. Uses the matched
+/// route pattern (e.g. `/:subreddit`) rather than the literal path, so the
+/// label doesn't carry one series per subreddit.
+pub async fn track_requests(req: Request<Body>, next: Next<Body>) -> Response {
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<axum::extract::MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+        .unwrap_or_else(|| req.uri().path().to_owned());
+
+    let res = next.run(req).await;
+
+    METRICS
+        .http_requests
+        .with_label_values(&[&method, &route, res.status().as_str()])
+        .inc();
+
+    res
+}
@@ -0,0 +1,225 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::HttpError;
+
+pub mod postgres;
+pub mod sled;
+pub mod sqlite;
+
+/// A subreddit configured for scraping, as stored by a [`Store`].
+#[derive(Debug, Clone)]
+pub struct SubredditRecord {
+    pub id: i64,
+    pub name: String,
+    pub time_cutoff_seconds: i64,
+    pub upvote_threshold: i64,
+}
+
+/// The upvote count for a post that already exists in the store, returned by
+/// [`Store::upsert_post`] so callers can tell whether it just crossed the
+/// threshold.
+#[derive(Debug, Clone, Copy)]
+pub struct ExistingPost {
+    pub ups: u32,
+}
+
+/// A single entry in a subreddit's public Atom feed.
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub title: String,
+    pub url: String,
+    pub threshold_passed: DateTime<Utc>,
+}
+
+/// A live WebSub subscription against one subreddit's feed, as registered
+/// through the `/hub` endpoint.
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub callback: String,
+    pub topic: String,
+    pub secret: Option<String>,
+    pub lease_expiry: DateTime<Utc>,
+}
+
+/// A due unit of work claimed from the `jobs` table. `rexml` only has one
+/// kind of job today (scraping a subreddit), so `payload` is simply an
+/// encoded [`crate::jobs::ScrapePayload`].
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: i64,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+/// A remote ActivityPub actor that has `Follow`ed a subreddit's feed actor,
+/// as recorded via the `/:subreddit/inbox` endpoint.
+#[derive(Debug, Clone)]
+pub struct Follower {
+    pub actor: String,
+    pub inbox: String,
+    pub shared_inbox: Option<String>,
+}
+
+/// Storage backend for subreddits and scraped posts.
+///
+/// `rexml` is hardwired to none of SQLite, Postgres or sled in particular:
+/// the binary picks an implementation at startup based on the scheme of
+/// `REXML_DB_URL` (`sqlite://`, `postgres://`/`postgresql://` or `sled://`)
+/// and threads it through the handlers and worker as `Arc<dyn Store>`.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// List every subreddit the worker should scrape.
+    async fn list_subreddits(&self) -> Result<Vec<SubredditRecord>, HttpError>;
+
+    /// Register a new subreddit to scrape, returning [`HttpError::AlreadyExists`]
+    /// if one with the same name is already registered.
+    async fn create_subreddit(
+        &self,
+        name: &str,
+        upvote_threshold: i64,
+        time_cutoff_seconds: i64,
+    ) -> Result<(), HttpError>;
+
+    /// Look up a subreddit's id by name, used to check it is registered
+    /// before serving its feed.
+    async fn subreddit_id(&self, name: &str) -> Result<Option<i64>, HttpError>;
+
+    /// Insert `reddit_id` if it isn't already known, returning the post's
+    /// previous upvote count when it was.
+    #[allow(clippy::too_many_arguments)]
+    async fn upsert_post(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        kind: &str,
+        title: &str,
+        url: &str,
+        permalink: &str,
+        created: DateTime<Utc>,
+        ups: u32,
+    ) -> Result<Option<ExistingPost>, HttpError>;
+
+    /// Record that a post has passed its subreddit's upvote threshold.
+    async fn mark_threshold_passed(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        ups: u32,
+        passed_at: DateTime<Utc>,
+    ) -> Result<(), HttpError>;
+
+    /// The most recent threshold-passing posts for a subreddit, newest first.
+    async fn feed_entries(&self, subreddit: &str, limit: i64) -> Result<Vec<FeedEntry>, HttpError>;
+
+    /// A page of threshold-passing posts for a subreddit, newest first,
+    /// skipping the first `offset` rows. Used by the ActivityPub outbox,
+    /// which (unlike the Atom feed) needs real pagination rather than just
+    /// the latest page.
+    async fn feed_entries_page(
+        &self,
+        subreddit: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<FeedEntry>, HttpError>;
+
+    /// Record a verified WebSub subscription, replacing any existing one for
+    /// the same `(callback, topic)` pair.
+    async fn upsert_subscription(&self, sub: &Subscription) -> Result<(), HttpError>;
+
+    /// Remove a WebSub subscription, e.g. on an `unsubscribe` request.
+    async fn remove_subscription(&self, callback: &str, topic: &str) -> Result<(), HttpError>;
+
+    /// Every subscription for `topic` whose lease hasn't expired, used to fan
+    /// out a distribution when a post newly passes its threshold.
+    async fn live_subscriptions(&self, topic: &str) -> Result<Vec<Subscription>, HttpError>;
+
+    /// Schedule a scrape described by `payload` (an encoded
+    /// [`crate::jobs::ScrapePayload`]) to run at or after `run_at`.
+    async fn enqueue_job(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError>;
+
+    /// Like [`Store::enqueue_job`], but a no-op if a job with the same
+    /// `payload` is already pending or running. Used to re-seed each
+    /// subreddit's schedule on startup without duplicating the queue on
+    /// every restart.
+    async fn enqueue_job_if_absent(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError>;
+
+    /// Atomically claim the earliest pending job due at or before `now`,
+    /// marking it running so no other claim can pick it up.
+    async fn claim_due_job(&self, now: DateTime<Utc>) -> Result<Option<Job>, HttpError>;
+
+    /// Mark a job done and remove it from the queue.
+    async fn complete_job(&self, id: i64) -> Result<(), HttpError>;
+
+    /// Put a failed job back in the pending state with its attempt count
+    /// incremented, to run again at `next_run_at`.
+    async fn retry_job(&self, id: i64, next_run_at: DateTime<Utc>) -> Result<(), HttpError>;
+
+    /// Mark a job permanently failed after it has exhausted its retries.
+    async fn fail_job(&self, id: i64) -> Result<(), HttpError>;
+
+    /// Sweep jobs that have been `running` since before `stale_before`
+    /// (i.e. claimed by a worker that crashed or was killed mid-job without
+    /// ever calling `complete_job`/`retry_job`/`fail_job`). The abandoned run
+    /// counts as an attempt: jobs whose attempt count, including it, stays
+    /// below `max_attempts` are reclaimed back to `pending` so they run again
+    /// immediately, and the rest are marked `failed` rather than retried
+    /// forever.
+    async fn reclaim_stale_jobs(&self, stale_before: DateTime<Utc>, max_attempts: i32) -> Result<(), HttpError>;
+
+    /// Persist a newly minted API token, identified by `label` and stored
+    /// only as its hash.
+    async fn create_token(
+        &self,
+        label: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), HttpError>;
+
+    /// Revoke a previously minted token by its label.
+    async fn revoke_token(&self, label: &str) -> Result<(), HttpError>;
+
+    /// Whether `token_hash` matches a live, unrevoked token.
+    async fn token_is_valid(&self, token_hash: &str) -> Result<bool, HttpError>;
+
+    /// The RSA keypair (PEM-encoded `(private, public)`) used to sign and
+    /// identify a subreddit's ActivityPub actor, if one has been generated
+    /// yet.
+    async fn actor_keypair(&self, subreddit_id: i64) -> Result<Option<(String, String)>, HttpError>;
+
+    /// Persist the keypair generated for a subreddit's ActivityPub actor the
+    /// first time it's requested, so it stays stable across restarts.
+    async fn store_actor_keypair(
+        &self,
+        subreddit_id: i64,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), HttpError>;
+
+    /// Record a remote actor's `Follow` of a subreddit's feed actor,
+    /// replacing any existing follower row for the same actor.
+    async fn add_follower(
+        &self,
+        subreddit_id: i64,
+        actor: &str,
+        inbox: &str,
+        shared_inbox: Option<&str>,
+    ) -> Result<(), HttpError>;
+
+    /// Every actor currently following a subreddit's feed actor, to fan a
+    /// `Create(Note)` out to when a post newly passes its threshold.
+    async fn followers(&self, subreddit_id: i64) -> Result<Vec<Follower>, HttpError>;
+}
+
+/// Connect to a [`Store`] implementation chosen by the scheme of `url`.
+pub async fn connect(url: &str) -> Result<std::sync::Arc<dyn Store>, Box<dyn std::error::Error>> {
+    if url.starts_with("sqlite://") {
+        Ok(std::sync::Arc::new(sqlite::SqliteStore::connect(url).await?))
+    } else if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        Ok(std::sync::Arc::new(postgres::PostgresStore::connect(url).await?))
+    } else if let Some(path) = url.strip_prefix("sled://") {
+        Ok(std::sync::Arc::new(sled::SledStore::open(path)?))
+    } else {
+        Err(format!("unrecognised scheme in REXML_DB_URL: {}", url).into())
+    }
+}
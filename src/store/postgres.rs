@@ -0,0 +1,435 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+
+use crate::store::{ExistingPost, FeedEntry, Follower, Job, Store, SubredditRecord, Subscription};
+use crate::HttpError;
+
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let pool = PgPoolOptions::new().max_connections(5).connect(url).await?;
+
+        sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn list_subreddits(&self) -> Result<Vec<SubredditRecord>, HttpError> {
+        let rows = sqlx::query("SELECT id, name, time_cutoff_seconds, upvote_threshold FROM subreddits")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SubredditRecord {
+                id: row.get("id"),
+                name: row.get("name"),
+                time_cutoff_seconds: row.get("time_cutoff_seconds"),
+                upvote_threshold: row.get("upvote_threshold"),
+            })
+            .collect())
+    }
+
+    async fn create_subreddit(
+        &self,
+        name: &str,
+        upvote_threshold: i64,
+        time_cutoff_seconds: i64,
+    ) -> Result<(), HttpError> {
+        let res = sqlx::query(
+            "INSERT INTO subreddits(name, upvote_threshold, time_cutoff_seconds) VALUES ($1,$2,$3)",
+        )
+        .bind(name)
+        .bind(upvote_threshold)
+        .bind(time_cutoff_seconds)
+        .execute(&self.pool)
+        .await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+                Err(HttpError::AlreadyExists)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn subreddit_id(&self, name: &str) -> Result<Option<i64>, HttpError> {
+        let row = sqlx::query("SELECT id FROM subreddits WHERE subreddits.name = $1 LIMIT 1")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("id")))
+    }
+
+    async fn upsert_post(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        kind: &str,
+        title: &str,
+        url: &str,
+        permalink: &str,
+        created: DateTime<Utc>,
+        ups: u32,
+    ) -> Result<Option<ExistingPost>, HttpError> {
+        let existing = sqlx::query("SELECT ups FROM posts WHERE reddit_id=$1")
+            .bind(reddit_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| ExistingPost {
+                ups: row.get::<i64, _>("ups") as u32,
+            });
+
+        if existing.is_none() {
+            sqlx::query(
+                "INSERT INTO posts(reddit_id, subreddit, kind, title, url, permalink, created, ups)
+                 VALUES ($1,$2,$3,$4,$5,$6,$7,$8)",
+            )
+            .bind(reddit_id)
+            .bind(subreddit_id)
+            .bind(kind)
+            .bind(title)
+            .bind(url)
+            .bind(permalink)
+            .bind(created)
+            .bind(ups as i64)
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(existing)
+    }
+
+    async fn mark_threshold_passed(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        ups: u32,
+        passed_at: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        sqlx::query(
+            "UPDATE posts SET ups = $1, threshold_passed = $2 WHERE reddit_id = $3 AND subreddit = $4",
+        )
+        .bind(ups as i64)
+        .bind(passed_at)
+        .bind(reddit_id)
+        .bind(subreddit_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn feed_entries(&self, subreddit: &str, limit: i64) -> Result<Vec<FeedEntry>, HttpError> {
+        let rows = sqlx::query(
+            "SELECT p.title, p.url, p.threshold_passed
+              FROM subreddits s
+              LEFT JOIN posts p ON p.subreddit = s.id
+              WHERE s.name = $1 AND p.threshold_passed IS NOT NULL
+              ORDER BY p.threshold_passed DESC
+              LIMIT $2",
+        )
+        .bind(subreddit)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FeedEntry {
+                title: row.get("title"),
+                url: row.get("url"),
+                threshold_passed: row.get("threshold_passed"),
+            })
+            .collect())
+    }
+
+    async fn feed_entries_page(
+        &self,
+        subreddit: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<FeedEntry>, HttpError> {
+        let rows = sqlx::query(
+            "SELECT p.title, p.url, p.threshold_passed
+              FROM subreddits s
+              LEFT JOIN posts p ON p.subreddit = s.id
+              WHERE s.name = $1 AND p.threshold_passed IS NOT NULL
+              ORDER BY p.threshold_passed DESC
+              LIMIT $2 OFFSET $3",
+        )
+        .bind(subreddit)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FeedEntry {
+                title: row.get("title"),
+                url: row.get("url"),
+                threshold_passed: row.get("threshold_passed"),
+            })
+            .collect())
+    }
+
+    async fn upsert_subscription(&self, sub: &Subscription) -> Result<(), HttpError> {
+        sqlx::query(
+            "INSERT INTO subscriptions(callback, topic, secret, lease_expiry) VALUES ($1,$2,$3,$4)
+             ON CONFLICT(callback, topic) DO UPDATE SET secret = excluded.secret, lease_expiry = excluded.lease_expiry",
+        )
+        .bind(&sub.callback)
+        .bind(&sub.topic)
+        .bind(&sub.secret)
+        .bind(sub.lease_expiry)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_subscription(&self, callback: &str, topic: &str) -> Result<(), HttpError> {
+        sqlx::query("DELETE FROM subscriptions WHERE callback = $1 AND topic = $2")
+            .bind(callback)
+            .bind(topic)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn live_subscriptions(&self, topic: &str) -> Result<Vec<Subscription>, HttpError> {
+        let rows = sqlx::query(
+            "SELECT callback, topic, secret, lease_expiry FROM subscriptions WHERE topic = $1 AND lease_expiry > now()",
+        )
+        .bind(topic)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Subscription {
+                callback: row.get("callback"),
+                topic: row.get("topic"),
+                secret: row.get("secret"),
+                lease_expiry: row.get("lease_expiry"),
+            })
+            .collect())
+    }
+
+    async fn enqueue_job(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        sqlx::query(
+            "INSERT INTO jobs(payload, state, attempts, next_run_at) VALUES ($1, 'pending', 0, $2)",
+        )
+        .bind(payload)
+        .bind(run_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn enqueue_job_if_absent(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        sqlx::query(
+            "INSERT INTO jobs(payload, state, attempts, next_run_at)
+             SELECT $1, 'pending', 0, $2
+              WHERE NOT EXISTS (
+                SELECT 1 FROM jobs WHERE payload = $1 AND state IN ('pending', 'running')
+              )",
+        )
+        .bind(payload)
+        .bind(run_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn claim_due_job(&self, now: DateTime<Utc>) -> Result<Option<Job>, HttpError> {
+        // `next_run_at` is reused as the claim timestamp while a job is
+        // `running`, so `reclaim_stale_jobs` can tell a job that's been
+        // running a while from one that was only just claimed.
+        let row = sqlx::query(
+            "UPDATE jobs SET state = 'running', next_run_at = $1
+              WHERE id = (
+                SELECT id FROM jobs
+                 WHERE state = 'pending' AND next_run_at <= $1
+                 ORDER BY next_run_at ASC LIMIT 1
+                 FOR UPDATE SKIP LOCKED
+              )
+              RETURNING id, payload, attempts",
+        )
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| Job {
+            id: row.get("id"),
+            payload: row.get("payload"),
+            attempts: row.get("attempts"),
+        }))
+    }
+
+    async fn complete_job(&self, id: i64) -> Result<(), HttpError> {
+        sqlx::query("DELETE FROM jobs WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn retry_job(&self, id: i64, next_run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        sqlx::query(
+            "UPDATE jobs SET state = 'pending', attempts = attempts + 1, next_run_at = $1 WHERE id = $2",
+        )
+        .bind(next_run_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn fail_job(&self, id: i64) -> Result<(), HttpError> {
+        sqlx::query("UPDATE jobs SET state = 'failed' WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn reclaim_stale_jobs(&self, stale_before: DateTime<Utc>, max_attempts: i32) -> Result<(), HttpError> {
+        sqlx::query(
+            "UPDATE jobs SET state = 'failed'
+              WHERE state = 'running' AND next_run_at <= $1 AND attempts + 1 >= $2",
+        )
+        .bind(stale_before)
+        .bind(max_attempts)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "UPDATE jobs SET state = 'pending', attempts = attempts + 1, next_run_at = $1
+              WHERE state = 'running' AND next_run_at <= $2",
+        )
+        .bind(Utc::now())
+        .bind(stale_before)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        label: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        sqlx::query(
+            "INSERT INTO tokens(label, token_hash, created_at, revoked) VALUES ($1,$2,$3,false)",
+        )
+        .bind(label)
+        .bind(token_hash)
+        .bind(created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_token(&self, label: &str) -> Result<(), HttpError> {
+        sqlx::query("UPDATE tokens SET revoked = true WHERE label = $1")
+            .bind(label)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn token_is_valid(&self, token_hash: &str) -> Result<bool, HttpError> {
+        let row = sqlx::query("SELECT revoked FROM tokens WHERE token_hash = $1 LIMIT 1")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(matches!(row, Some(row) if !row.get::<bool, _>("revoked")))
+    }
+
+    async fn actor_keypair(&self, subreddit_id: i64) -> Result<Option<(String, String)>, HttpError> {
+        let row = sqlx::query("SELECT private_key_pem, public_key_pem FROM actor_keys WHERE subreddit_id = $1")
+            .bind(subreddit_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| (row.get("private_key_pem"), row.get("public_key_pem"))))
+    }
+
+    async fn store_actor_keypair(
+        &self,
+        subreddit_id: i64,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), HttpError> {
+        sqlx::query(
+            "INSERT INTO actor_keys(subreddit_id, private_key_pem, public_key_pem) VALUES ($1,$2,$3)",
+        )
+        .bind(subreddit_id)
+        .bind(private_key_pem)
+        .bind(public_key_pem)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn add_follower(
+        &self,
+        subreddit_id: i64,
+        actor: &str,
+        inbox: &str,
+        shared_inbox: Option<&str>,
+    ) -> Result<(), HttpError> {
+        sqlx::query(
+            "INSERT INTO followers(subreddit_id, actor, inbox, shared_inbox) VALUES ($1,$2,$3,$4)
+             ON CONFLICT(subreddit_id, actor) DO UPDATE SET inbox = excluded.inbox, shared_inbox = excluded.shared_inbox",
+        )
+        .bind(subreddit_id)
+        .bind(actor)
+        .bind(inbox)
+        .bind(shared_inbox)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn followers(&self, subreddit_id: i64) -> Result<Vec<Follower>, HttpError> {
+        let rows = sqlx::query("SELECT actor, inbox, shared_inbox FROM followers WHERE subreddit_id = $1")
+            .bind(subreddit_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Follower {
+                actor: row.get("actor"),
+                inbox: row.get("inbox"),
+                shared_inbox: row.get("shared_inbox"),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,683 @@
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::store::{ExistingPost, FeedEntry, Follower, Job, Store, SubredditRecord, Subscription};
+use crate::HttpError;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SubredditRow {
+    id: i64,
+    time_cutoff_seconds: i64,
+    upvote_threshold: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PostRow {
+    subreddit_id: i64,
+    kind: String,
+    title: String,
+    url: String,
+    permalink: String,
+    created: i64,
+    ups: u32,
+    threshold_passed: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FeedEntryRow {
+    title: String,
+    url: String,
+    permalink: String,
+    created: i64,
+    threshold_passed: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SubscriptionRow {
+    callback: String,
+    topic: String,
+    secret: Option<String>,
+    lease_expiry: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum JobState {
+    Pending,
+    Running,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JobRow {
+    payload: String,
+    state: JobState,
+    attempts: i32,
+    next_run_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TokenRow {
+    label: String,
+    created_at: i64,
+    revoked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ActorKeyRow {
+    private_key_pem: String,
+    public_key_pem: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FollowerRow {
+    actor: String,
+    inbox: String,
+    shared_inbox: Option<String>,
+}
+
+/// An embedded [`Store`] backed by `sled`, for running `rexml` without a SQL
+/// server. Selected when `REXML_DB_URL` starts with `sled://`.
+pub struct SledStore {
+    subreddits: sled::Tree,
+    posts: sled::Tree,
+    feed: sled::Tree,
+    subscriptions: sled::Tree,
+    jobs: sled::Tree,
+    tokens: sled::Tree,
+    actor_keys: sled::Tree,
+    followers: sled::Tree,
+    db: sled::Db,
+}
+
+impl SledStore {
+    pub fn open(path: &str) -> Result<Self, sled::Error> {
+        let db = sled::open(path)?;
+        let subreddits = db.open_tree("subreddits")?;
+        let posts = db.open_tree("posts")?;
+        let feed = db.open_tree("feed")?;
+        let subscriptions = db.open_tree("subscriptions")?;
+        let jobs = db.open_tree("jobs")?;
+        let tokens = db.open_tree("tokens")?;
+        let actor_keys = db.open_tree("actor_keys")?;
+        let followers = db.open_tree("followers")?;
+
+        Ok(Self {
+            subreddits,
+            posts,
+            feed,
+            subscriptions,
+            jobs,
+            tokens,
+            actor_keys,
+            followers,
+            db,
+        })
+    }
+
+    fn subscription_key(callback: &str, topic: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(callback.len() + topic.len() + 1);
+        key.extend_from_slice(callback.as_bytes());
+        key.push(0);
+        key.extend_from_slice(topic.as_bytes());
+        key
+    }
+
+    fn follower_key(subreddit_id: i64, actor: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(8 + actor.len());
+        key.extend_from_slice(&subreddit_id.to_be_bytes());
+        key.extend_from_slice(actor.as_bytes());
+        key
+    }
+
+    fn feed_key(subreddit_id: i64, threshold_passed: i64, reddit_id: &str) -> Vec<u8> {
+        let mut key = Vec::with_capacity(16 + reddit_id.len());
+        key.extend_from_slice(&subreddit_id.to_be_bytes());
+        key.extend_from_slice(&threshold_passed.to_be_bytes());
+        key.extend_from_slice(reddit_id.as_bytes());
+        key
+    }
+}
+
+#[async_trait]
+impl Store for SledStore {
+    async fn list_subreddits(&self) -> Result<Vec<SubredditRecord>, HttpError> {
+        self.subreddits
+            .iter()
+            .map(|entry| {
+                let (name, value) = entry.map_err(|e| HttpError::Other(Box::new(e)))?;
+                let row: SubredditRow =
+                    serde_json::from_slice(&value).map_err(|e| HttpError::Other(Box::new(e)))?;
+                Ok(SubredditRecord {
+                    id: row.id,
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    time_cutoff_seconds: row.time_cutoff_seconds,
+                    upvote_threshold: row.upvote_threshold,
+                })
+            })
+            .collect()
+    }
+
+    async fn create_subreddit(
+        &self,
+        name: &str,
+        upvote_threshold: i64,
+        time_cutoff_seconds: i64,
+    ) -> Result<(), HttpError> {
+        if self.subreddits.contains_key(name).map_err(|e| HttpError::Other(Box::new(e)))? {
+            return Err(HttpError::AlreadyExists);
+        }
+
+        let id = self.db.generate_id().map_err(|e| HttpError::Other(Box::new(e)))? as i64;
+        let row = SubredditRow {
+            id,
+            time_cutoff_seconds,
+            upvote_threshold,
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        self.subreddits
+            .insert(name, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn subreddit_id(&self, name: &str) -> Result<Option<i64>, HttpError> {
+        let row = self
+            .subreddits
+            .get(name)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        row.map(|value| {
+            serde_json::from_slice::<SubredditRow>(&value)
+                .map(|row| row.id)
+                .map_err(|e| HttpError::Other(Box::new(e)))
+        })
+        .transpose()
+    }
+
+    async fn upsert_post(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        kind: &str,
+        title: &str,
+        url: &str,
+        permalink: &str,
+        created: DateTime<Utc>,
+        ups: u32,
+    ) -> Result<Option<ExistingPost>, HttpError> {
+        if let Some(value) = self.posts.get(reddit_id).map_err(|e| HttpError::Other(Box::new(e)))? {
+            let row: PostRow =
+                serde_json::from_slice(&value).map_err(|e| HttpError::Other(Box::new(e)))?;
+            return Ok(Some(ExistingPost { ups: row.ups }));
+        }
+
+        let row = PostRow {
+            subreddit_id,
+            kind: kind.to_owned(),
+            title: title.to_owned(),
+            url: url.to_owned(),
+            permalink: permalink.to_owned(),
+            created: created.timestamp(),
+            ups,
+            threshold_passed: None,
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+        self.posts
+            .insert(reddit_id, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(None)
+    }
+
+    async fn mark_threshold_passed(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        ups: u32,
+        passed_at: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        let existing = self
+            .posts
+            .get(reddit_id)
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .map(|value| serde_json::from_slice::<PostRow>(&value))
+            .transpose()
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .ok_or(HttpError::NotFound)?;
+
+        let row = PostRow {
+            ups,
+            threshold_passed: Some(passed_at.timestamp()),
+            ..existing
+        };
+
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+        self.posts
+            .insert(reddit_id, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        let entry = FeedEntryRow {
+            title: row.title,
+            url: row.url,
+            permalink: row.permalink,
+            created: row.created,
+            threshold_passed: passed_at.timestamp(),
+        };
+        let entry_value = serde_json::to_vec(&entry).map_err(|e| HttpError::Other(Box::new(e)))?;
+        self.feed
+            .insert(Self::feed_key(subreddit_id, passed_at.timestamp(), reddit_id), entry_value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn feed_entries(&self, subreddit: &str, limit: i64) -> Result<Vec<FeedEntry>, HttpError> {
+        let subreddit_id = self
+            .subreddit_id(subreddit)
+            .await?
+            .ok_or(HttpError::NotFound)?;
+
+        let start = Self::feed_key(subreddit_id, 0, "");
+        let end = Self::feed_key(subreddit_id, i64::MAX, "\u{10FFFF}");
+
+        let mut entries: Vec<FeedEntry> = self
+            .feed
+            .range(start..=end)
+            .map(|entry| {
+                let (_, value) = entry.map_err(|e| HttpError::Other(Box::new(e)))?;
+                let row: FeedEntryRow =
+                    serde_json::from_slice(&value).map_err(|e| HttpError::Other(Box::new(e)))?;
+                Ok(FeedEntry {
+                    title: row.title,
+                    url: row.url,
+                    threshold_passed: Utc.timestamp_opt(row.threshold_passed, 0).unwrap(),
+                })
+            })
+            .collect::<Result<_, HttpError>>()?;
+
+        entries.sort_by(|a, b| b.threshold_passed.cmp(&a.threshold_passed));
+        entries.truncate(limit as usize);
+
+        Ok(entries)
+    }
+
+    async fn feed_entries_page(
+        &self,
+        subreddit: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<FeedEntry>, HttpError> {
+        // `feed_entries` already returns every entry sorted newest-first when
+        // given a limit this large; slice out the requested page from that.
+        let entries = self.feed_entries(subreddit, i64::MAX).await?;
+
+        Ok(entries
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn upsert_subscription(&self, sub: &Subscription) -> Result<(), HttpError> {
+        let row = SubscriptionRow {
+            callback: sub.callback.clone(),
+            topic: sub.topic.clone(),
+            secret: sub.secret.clone(),
+            lease_expiry: sub.lease_expiry.timestamp(),
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        self.subscriptions
+            .insert(Self::subscription_key(&sub.callback, &sub.topic), value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn remove_subscription(&self, callback: &str, topic: &str) -> Result<(), HttpError> {
+        self.subscriptions
+            .remove(Self::subscription_key(callback, topic))
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn live_subscriptions(&self, topic: &str) -> Result<Vec<Subscription>, HttpError> {
+        let now = Utc::now().timestamp();
+
+        self.subscriptions
+            .iter()
+            .filter_map(|entry| {
+                let (_, value) = match entry {
+                    Ok(kv) => kv,
+                    Err(e) => return Some(Err(HttpError::Other(Box::new(e)))),
+                };
+                let row: SubscriptionRow = match serde_json::from_slice(&value) {
+                    Ok(row) => row,
+                    Err(e) => return Some(Err(HttpError::Other(Box::new(e)))),
+                };
+
+                if row.topic != topic || row.lease_expiry <= now {
+                    return None;
+                }
+
+                Some(Ok(Subscription {
+                    callback: row.callback,
+                    topic: row.topic,
+                    secret: row.secret,
+                    lease_expiry: Utc.timestamp_opt(row.lease_expiry, 0).unwrap(),
+                }))
+            })
+            .collect()
+    }
+
+    async fn enqueue_job(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        let id = self.db.generate_id().map_err(|e| HttpError::Other(Box::new(e)))?;
+        let row = JobRow {
+            payload: payload.to_owned(),
+            state: JobState::Pending,
+            attempts: 0,
+            next_run_at: run_at.timestamp(),
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        self.jobs
+            .insert(id.to_be_bytes(), value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn enqueue_job_if_absent(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        let already_queued = self
+            .jobs
+            .iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|(_, value)| serde_json::from_slice::<JobRow>(&value).ok())
+            .any(|row| row.payload == payload && row.state != JobState::Failed);
+
+        if already_queued {
+            return Ok(());
+        }
+
+        self.enqueue_job(payload, run_at).await
+    }
+
+    async fn claim_due_job(&self, now: DateTime<Utc>) -> Result<Option<Job>, HttpError> {
+        let now = now.timestamp();
+
+        let due = self
+            .jobs
+            .iter()
+            .filter_map(|entry| {
+                let (key, value) = match entry {
+                    Ok(kv) => kv,
+                    Err(e) => return Some(Err(HttpError::Other(Box::new(e)))),
+                };
+                let row: JobRow = match serde_json::from_slice(&value) {
+                    Ok(row) => row,
+                    Err(e) => return Some(Err(HttpError::Other(Box::new(e)))),
+                };
+
+                if row.state != JobState::Pending || row.next_run_at > now {
+                    return None;
+                }
+
+                Some(Ok((key, value, row)))
+            })
+            .collect::<Result<Vec<_>, HttpError>>()?
+            .into_iter()
+            .min_by_key(|(_, _, row)| row.next_run_at);
+
+        let Some((key, old_value, mut row)) = due else {
+            return Ok(None);
+        };
+
+        // `next_run_at` is reused as the claim timestamp while a job is
+        // `running`, so `reclaim_stale_jobs` can tell a job that's been
+        // running a while from one that was only just claimed.
+        row.state = JobState::Running;
+        row.next_run_at = now;
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+        // Only take the job if it is unchanged since it was read, so a
+        // concurrent claim can't pick up the same job.
+        let swapped = self
+            .jobs
+            .compare_and_swap(&key, Some(&old_value), Some(value))
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+        if swapped.is_err() {
+            return Ok(None);
+        }
+
+        let id = i64::from_be_bytes(key.as_ref().try_into().unwrap());
+
+        Ok(Some(Job {
+            id,
+            payload: row.payload,
+            attempts: row.attempts,
+        }))
+    }
+
+    async fn complete_job(&self, id: i64) -> Result<(), HttpError> {
+        self.jobs
+            .remove(id.to_be_bytes())
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn retry_job(&self, id: i64, next_run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        let key = id.to_be_bytes();
+        let mut row: JobRow = self
+            .jobs
+            .get(key)
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .ok_or(HttpError::NotFound)?;
+
+        row.state = JobState::Pending;
+        row.attempts += 1;
+        row.next_run_at = next_run_at.timestamp();
+
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+        self.jobs
+            .insert(key, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn fail_job(&self, id: i64) -> Result<(), HttpError> {
+        let key = id.to_be_bytes();
+        let mut row: JobRow = self
+            .jobs
+            .get(key)
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .ok_or(HttpError::NotFound)?;
+
+        row.state = JobState::Failed;
+
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+        self.jobs
+            .insert(key, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn reclaim_stale_jobs(&self, stale_before: DateTime<Utc>, max_attempts: i32) -> Result<(), HttpError> {
+        let stale_before = stale_before.timestamp();
+        let now = Utc::now().timestamp();
+
+        let stale: Vec<(sled::IVec, JobRow)> = self
+            .jobs
+            .iter()
+            .filter_map(|entry| {
+                let (key, value) = match entry {
+                    Ok(kv) => kv,
+                    Err(e) => return Some(Err(HttpError::Other(Box::new(e)))),
+                };
+                let row: JobRow = match serde_json::from_slice(&value) {
+                    Ok(row) => row,
+                    Err(e) => return Some(Err(HttpError::Other(Box::new(e)))),
+                };
+
+                if row.state != JobState::Running || row.next_run_at > stale_before {
+                    return None;
+                }
+
+                Some(Ok((key, row)))
+            })
+            .collect::<Result<Vec<_>, HttpError>>()?;
+
+        for (key, mut row) in stale {
+            if row.attempts + 1 >= max_attempts {
+                row.state = JobState::Failed;
+            } else {
+                row.state = JobState::Pending;
+                row.attempts += 1;
+                row.next_run_at = now;
+            }
+
+            let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+            self.jobs
+                .insert(&key, value)
+                .map_err(|e| HttpError::Other(Box::new(e)))?;
+        }
+
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        label: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        let row = TokenRow {
+            label: label.to_owned(),
+            created_at: created_at.timestamp(),
+            revoked: false,
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        self.tokens
+            .insert(token_hash, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn revoke_token(&self, label: &str) -> Result<(), HttpError> {
+        let found = self
+            .tokens
+            .iter()
+            .filter_map(|entry| entry.ok())
+            .find_map(|(key, value)| {
+                let row: TokenRow = serde_json::from_slice(&value).ok()?;
+                (row.label == label).then_some((key, row))
+            });
+
+        let Some((key, mut row)) = found else {
+            return Ok(());
+        };
+
+        row.revoked = true;
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+        self.tokens
+            .insert(key, value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn token_is_valid(&self, token_hash: &str) -> Result<bool, HttpError> {
+        let row = self
+            .tokens
+            .get(token_hash)
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .map(|value| serde_json::from_slice::<TokenRow>(&value))
+            .transpose()
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(row.map_or(false, |row| !row.revoked))
+    }
+
+    async fn actor_keypair(&self, subreddit_id: i64) -> Result<Option<(String, String)>, HttpError> {
+        let row = self
+            .actor_keys
+            .get(subreddit_id.to_be_bytes())
+            .map_err(|e| HttpError::Other(Box::new(e)))?
+            .map(|value| serde_json::from_slice::<ActorKeyRow>(&value))
+            .transpose()
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(row.map(|row| (row.private_key_pem, row.public_key_pem)))
+    }
+
+    async fn store_actor_keypair(
+        &self,
+        subreddit_id: i64,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), HttpError> {
+        let row = ActorKeyRow {
+            private_key_pem: private_key_pem.to_owned(),
+            public_key_pem: public_key_pem.to_owned(),
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        self.actor_keys
+            .insert(subreddit_id.to_be_bytes(), value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn add_follower(
+        &self,
+        subreddit_id: i64,
+        actor: &str,
+        inbox: &str,
+        shared_inbox: Option<&str>,
+    ) -> Result<(), HttpError> {
+        let row = FollowerRow {
+            actor: actor.to_owned(),
+            inbox: inbox.to_owned(),
+            shared_inbox: shared_inbox.map(str::to_owned),
+        };
+        let value = serde_json::to_vec(&row).map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        self.followers
+            .insert(Self::follower_key(subreddit_id, actor), value)
+            .map_err(|e| HttpError::Other(Box::new(e)))?;
+
+        Ok(())
+    }
+
+    async fn followers(&self, subreddit_id: i64) -> Result<Vec<Follower>, HttpError> {
+        let prefix = subreddit_id.to_be_bytes();
+
+        self.followers
+            .scan_prefix(prefix)
+            .map(|entry| {
+                let (_, value) = entry.map_err(|e| HttpError::Other(Box::new(e)))?;
+                let row: FollowerRow =
+                    serde_json::from_slice(&value).map_err(|e| HttpError::Other(Box::new(e)))?;
+                Ok(Follower {
+                    actor: row.actor,
+                    inbox: row.inbox,
+                    shared_inbox: row.shared_inbox,
+                })
+            })
+            .collect()
+    }
+}
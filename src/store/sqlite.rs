@@ -0,0 +1,500 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::SqlitePool;
+
+use crate::store::{ExistingPost, FeedEntry, Follower, Job, Store, SubredditRecord, Subscription};
+use crate::HttpError;
+
+fn timestamp_to_utc(ts: i64) -> DateTime<Utc> {
+    DateTime::from_utc(NaiveDateTime::from_timestamp(ts, 0), Utc)
+}
+
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect(url).await?;
+
+        let mut conn = pool.acquire().await?;
+        sqlx::migrate!("./migrations/sqlite").run(&mut conn).await?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn list_subreddits(&self) -> Result<Vec<SubredditRecord>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query!("SELECT id, name, time_cutoff_seconds, upvote_threshold FROM subreddits")
+            .fetch_all(&mut conn)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SubredditRecord {
+                id: row.id,
+                name: row.name,
+                time_cutoff_seconds: row.time_cutoff_seconds,
+                upvote_threshold: row.upvote_threshold,
+            })
+            .collect())
+    }
+
+    async fn create_subreddit(
+        &self,
+        name: &str,
+        upvote_threshold: i64,
+        time_cutoff_seconds: i64,
+    ) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let res = sqlx::query!(
+            "INSERT INTO subreddits(name, upvote_threshold, time_cutoff_seconds) VALUES (?,?,?)",
+            name,
+            upvote_threshold,
+            time_cutoff_seconds
+        )
+        .execute(&mut conn)
+        .await;
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("2067") => {
+                Err(HttpError::AlreadyExists)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn subreddit_id(&self, name: &str) -> Result<Option<i64>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query!("SELECT id FROM subreddits WHERE subreddits.name = ? LIMIT 1", name)
+            .fetch_optional(&mut conn)
+            .await?;
+
+        Ok(row.map(|row| row.id))
+    }
+
+    async fn upsert_post(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        kind: &str,
+        title: &str,
+        url: &str,
+        permalink: &str,
+        created: DateTime<Utc>,
+        ups: u32,
+    ) -> Result<Option<ExistingPost>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+
+        let existing = sqlx::query!("SELECT ups FROM posts WHERE reddit_id=?", reddit_id)
+            .fetch_optional(&mut conn)
+            .await?
+            .map(|row| ExistingPost { ups: row.ups as u32 });
+
+        if existing.is_none() {
+            let created = created.timestamp();
+            sqlx::query!(
+                "INSERT INTO posts(reddit_id, subreddit, kind, title, url, permalink, created, ups)
+                 VALUES (?,?,?,?,?,?,?,?)",
+                reddit_id,
+                subreddit_id,
+                kind,
+                title,
+                url,
+                permalink,
+                created,
+                ups
+            )
+            .execute(&mut conn)
+            .await?;
+        }
+
+        Ok(existing)
+    }
+
+    async fn mark_threshold_passed(
+        &self,
+        subreddit_id: i64,
+        reddit_id: &str,
+        ups: u32,
+        passed_at: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let passed_at = passed_at.timestamp();
+
+        sqlx::query!(
+            "UPDATE posts SET ups = ?, threshold_passed = ? WHERE reddit_id = ? AND subreddit = ?",
+            ups,
+            passed_at,
+            reddit_id,
+            subreddit_id,
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn feed_entries(&self, subreddit: &str, limit: i64) -> Result<Vec<FeedEntry>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query!(
+            "SELECT p.title, p.url, p.threshold_passed
+              FROM subreddits s
+              LEFT JOIN posts p ON p.subreddit = s.id
+              WHERE s.name = ? AND p.threshold_passed IS NOT NULL
+              ORDER BY p.threshold_passed DESC
+              LIMIT ?",
+            subreddit,
+            limit
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FeedEntry {
+                title: row.title.unwrap(),
+                url: row.url.unwrap(),
+                threshold_passed: timestamp_to_utc(row.threshold_passed.unwrap()),
+            })
+            .collect())
+    }
+
+    async fn feed_entries_page(
+        &self,
+        subreddit: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<FeedEntry>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query!(
+            "SELECT p.title, p.url, p.threshold_passed
+              FROM subreddits s
+              LEFT JOIN posts p ON p.subreddit = s.id
+              WHERE s.name = ? AND p.threshold_passed IS NOT NULL
+              ORDER BY p.threshold_passed DESC
+              LIMIT ? OFFSET ?",
+            subreddit,
+            limit,
+            offset
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FeedEntry {
+                title: row.title.unwrap(),
+                url: row.url.unwrap(),
+                threshold_passed: timestamp_to_utc(row.threshold_passed.unwrap()),
+            })
+            .collect())
+    }
+
+    async fn upsert_subscription(&self, sub: &Subscription) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let lease_expiry = sub.lease_expiry.timestamp();
+
+        sqlx::query!(
+            "INSERT INTO subscriptions(callback, topic, secret, lease_expiry) VALUES (?,?,?,?)
+             ON CONFLICT(callback, topic) DO UPDATE SET secret = excluded.secret, lease_expiry = excluded.lease_expiry",
+            sub.callback,
+            sub.topic,
+            sub.secret,
+            lease_expiry,
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_subscription(&self, callback: &str, topic: &str) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+
+        sqlx::query!(
+            "DELETE FROM subscriptions WHERE callback = ? AND topic = ?",
+            callback,
+            topic
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn live_subscriptions(&self, topic: &str) -> Result<Vec<Subscription>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let now = Utc::now().timestamp();
+
+        let rows = sqlx::query!(
+            "SELECT callback, topic, secret, lease_expiry FROM subscriptions WHERE topic = ? AND lease_expiry > ?",
+            topic,
+            now
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Subscription {
+                callback: row.callback,
+                topic: row.topic,
+                secret: row.secret,
+                lease_expiry: timestamp_to_utc(row.lease_expiry),
+            })
+            .collect())
+    }
+
+    async fn enqueue_job(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let run_at = run_at.timestamp();
+
+        sqlx::query!(
+            "INSERT INTO jobs(payload, state, attempts, next_run_at) VALUES (?, 'pending', 0, ?)",
+            payload,
+            run_at
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn enqueue_job_if_absent(&self, payload: &str, run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let run_at = run_at.timestamp();
+
+        sqlx::query!(
+            "INSERT INTO jobs(payload, state, attempts, next_run_at)
+             SELECT ?, 'pending', 0, ?
+              WHERE NOT EXISTS (
+                SELECT 1 FROM jobs WHERE payload = ? AND state IN ('pending', 'running')
+              )",
+            payload,
+            run_at,
+            payload
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn claim_due_job(&self, now: DateTime<Utc>) -> Result<Option<Job>, HttpError> {
+        let mut tx = self.pool.begin().await?;
+        let now = now.timestamp();
+
+        let row = sqlx::query!(
+            "SELECT id, payload, attempts FROM jobs
+              WHERE state = 'pending' AND next_run_at <= ?
+              ORDER BY next_run_at ASC LIMIT 1",
+            now
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        // `next_run_at` is reused as the claim timestamp while a job is
+        // `running`, so `reclaim_stale_jobs` can tell a job that's been
+        // running a while from one that was only just claimed.
+        sqlx::query!(
+            "UPDATE jobs SET state = 'running', next_run_at = ? WHERE id = ?",
+            now,
+            row.id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(Job {
+            id: row.id,
+            payload: row.payload,
+            attempts: row.attempts as i32,
+        }))
+    }
+
+    async fn complete_job(&self, id: i64) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query!("DELETE FROM jobs WHERE id = ?", id)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn retry_job(&self, id: i64, next_run_at: DateTime<Utc>) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let next_run_at = next_run_at.timestamp();
+
+        sqlx::query!(
+            "UPDATE jobs SET state = 'pending', attempts = attempts + 1, next_run_at = ? WHERE id = ?",
+            next_run_at,
+            id
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn fail_job(&self, id: i64) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query!("UPDATE jobs SET state = 'failed' WHERE id = ?", id)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn reclaim_stale_jobs(&self, stale_before: DateTime<Utc>, max_attempts: i32) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let stale_before = stale_before.timestamp();
+        let now = Utc::now().timestamp();
+
+        sqlx::query!(
+            "UPDATE jobs SET state = 'failed'
+              WHERE state = 'running' AND next_run_at <= ? AND attempts + 1 >= ?",
+            stale_before,
+            max_attempts
+        )
+        .execute(&mut conn)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE jobs SET state = 'pending', attempts = attempts + 1, next_run_at = ?
+              WHERE state = 'running' AND next_run_at <= ?",
+            now,
+            stale_before
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn create_token(
+        &self,
+        label: &str,
+        token_hash: &str,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let created_at = created_at.timestamp();
+
+        sqlx::query!(
+            "INSERT INTO tokens(label, token_hash, created_at, revoked) VALUES (?,?,?,0)",
+            label,
+            token_hash,
+            created_at
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_token(&self, label: &str) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+
+        sqlx::query!("UPDATE tokens SET revoked = 1 WHERE label = ?", label)
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn token_is_valid(&self, token_hash: &str) -> Result<bool, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+
+        let row = sqlx::query!(
+            "SELECT revoked FROM tokens WHERE token_hash = ? LIMIT 1",
+            token_hash
+        )
+        .fetch_optional(&mut conn)
+        .await?;
+
+        Ok(matches!(row, Some(row) if row.revoked == 0))
+    }
+
+    async fn actor_keypair(&self, subreddit_id: i64) -> Result<Option<(String, String)>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let row = sqlx::query!(
+            "SELECT private_key_pem, public_key_pem FROM actor_keys WHERE subreddit_id = ?",
+            subreddit_id
+        )
+        .fetch_optional(&mut conn)
+        .await?;
+
+        Ok(row.map(|row| (row.private_key_pem, row.public_key_pem)))
+    }
+
+    async fn store_actor_keypair(
+        &self,
+        subreddit_id: i64,
+        private_key_pem: &str,
+        public_key_pem: &str,
+    ) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query!(
+            "INSERT INTO actor_keys(subreddit_id, private_key_pem, public_key_pem) VALUES (?,?,?)",
+            subreddit_id,
+            private_key_pem,
+            public_key_pem
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn add_follower(
+        &self,
+        subreddit_id: i64,
+        actor: &str,
+        inbox: &str,
+        shared_inbox: Option<&str>,
+    ) -> Result<(), HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query!(
+            "INSERT INTO followers(subreddit_id, actor, inbox, shared_inbox) VALUES (?,?,?,?)
+             ON CONFLICT(subreddit_id, actor) DO UPDATE SET inbox = excluded.inbox, shared_inbox = excluded.shared_inbox",
+            subreddit_id,
+            actor,
+            inbox,
+            shared_inbox
+        )
+        .execute(&mut conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn followers(&self, subreddit_id: i64) -> Result<Vec<Follower>, HttpError> {
+        let mut conn = self.pool.acquire().await?;
+        let rows = sqlx::query!(
+            "SELECT actor, inbox, shared_inbox FROM followers WHERE subreddit_id = ?",
+            subreddit_id
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| Follower {
+                actor: row.actor,
+                inbox: row.inbox,
+                shared_inbox: row.shared_inbox,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Form};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Deserialize;
+use sha1::Sha1;
+use tracing::{error, info, instrument, warn};
+
+use crate::store::{Store, Subscription};
+use crate::HttpError;
+
+/// Default lease for a subscription when `hub.lease_seconds` isn't given, as
+/// recommended by the WebSub spec (10 days).
+const DEFAULT_LEASE_SECONDS: i64 = 10 * 24 * 60 * 60;
+
+/// Timeout applied to every outbound request this module makes (the
+/// verification handshake and update deliveries). `hub.callback` is fully
+/// attacker-controlled on the unauthenticated `POST /hub` endpoint, so a
+/// subscriber that never answers would otherwise wedge the `/hub` request
+/// awaiting its verification, or a `distribute` task, forever.
+const HTTP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Shared client for every outbound WebSub request, per the rationale in
+/// `cache.rs`: one `Client` reused across calls, rather than a bare
+/// `Client::new()` (and its missing timeout) built fresh each time.
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .expect("reqwest client with a timeout can always be built")
+});
+
+#[derive(Debug, Deserialize)]
+pub struct HubRequest {
+    #[serde(rename = "hub.mode")]
+    mode: String,
+    #[serde(rename = "hub.topic")]
+    topic: String,
+    #[serde(rename = "hub.callback")]
+    callback: String,
+    #[serde(rename = "hub.secret")]
+    secret: Option<String>,
+    #[serde(rename = "hub.lease_seconds")]
+    lease_seconds: Option<i64>,
+}
+
+/// `POST /hub` — the WebSub subscribe/unsubscribe endpoint.
+#[instrument(skip(store))]
+pub async fn hub_handler(
+    Extension(store): Extension<Arc<dyn Store>>,
+    Form(req): Form<HubRequest>,
+) -> Result<impl IntoResponse, HttpError> {
+    match req.mode.as_str() {
+        "subscribe" => {
+            if !verify_intent(&req.callback, "subscribe", &req.topic, req.lease_seconds).await {
+                warn!(callback = %req.callback, %req.topic, "subscription verification failed");
+                return Ok(StatusCode::BAD_REQUEST);
+            }
+
+            let lease_expiry =
+                Utc::now() + Duration::seconds(req.lease_seconds.unwrap_or(DEFAULT_LEASE_SECONDS));
+
+            store
+                .upsert_subscription(&Subscription {
+                    callback: req.callback.clone(),
+                    topic: req.topic.clone(),
+                    secret: req.secret,
+                    lease_expiry,
+                })
+                .await?;
+
+            info!(callback = %req.callback, %req.topic, "subscribed");
+            Ok(StatusCode::ACCEPTED)
+        }
+        "unsubscribe" => {
+            if !verify_intent(&req.callback, "unsubscribe", &req.topic, req.lease_seconds).await {
+                warn!(callback = %req.callback, %req.topic, "unsubscribe verification failed");
+                return Ok(StatusCode::BAD_REQUEST);
+            }
+
+            store.remove_subscription(&req.callback, &req.topic).await?;
+
+            info!(callback = %req.callback, %req.topic, "unsubscribed");
+            Ok(StatusCode::ACCEPTED)
+        }
+        mode => {
+            warn!(%mode, "unrecognised hub.mode");
+            Ok(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+/// Perform the WebSub verification handshake: GET `callback` with a random
+/// `hub.challenge` and only trust the (un)subscription if it's echoed back
+/// verbatim.
+async fn verify_intent(callback: &str, mode: &str, topic: &str, lease_seconds: Option<i64>) -> bool {
+    let challenge: u64 = rand::thread_rng().gen();
+    let challenge = challenge.to_string();
+
+    let client = &*CLIENT;
+    let mut query = vec![
+        ("hub.mode", mode.to_string()),
+        ("hub.topic", topic.to_string()),
+        ("hub.challenge", challenge.clone()),
+    ];
+    if let Some(lease_seconds) = lease_seconds {
+        query.push(("hub.lease_seconds", lease_seconds.to_string()));
+    }
+
+    let res = match client.get(callback).query(&query).send().await {
+        Ok(res) => res,
+        Err(e) => {
+            error!(%callback, %e, "failed to reach subscriber for verification");
+            return false;
+        }
+    };
+
+    match res.text().await {
+        Ok(body) if body == challenge => true,
+        Ok(_) => false,
+        Err(e) => {
+            error!(%callback, %e, "failed to read verification response");
+            false
+        }
+    }
+}
+
+/// Deliver `body` (the updated Atom document for `topic`) to every live
+/// subscriber, signing it with each subscriber's stored secret.
+#[instrument(skip(store, body))]
+pub async fn distribute(store: &dyn Store, topic: &str, body: Vec<u8>) {
+    let subs = match store.live_subscriptions(topic).await {
+        Ok(subs) => subs,
+        Err(e) => {
+            error!(%topic, %e, "failed to load subscriptions");
+            return;
+        }
+    };
+
+    let client = &*CLIENT;
+    for sub in subs {
+        let mut req = client
+            .post(&sub.callback)
+            .header("Content-Type", "application/atom+xml")
+            .body(body.clone());
+
+        if let Some(secret) = &sub.secret {
+            req = req.header("X-Hub-Signature", format!("sha1={}", sign(secret, &body)));
+        }
+
+        match req.send().await {
+            Ok(res) if res.status().is_success() => {
+                info!(callback = %sub.callback, %topic, "delivered update");
+            }
+            Ok(res) => {
+                warn!(callback = %sub.callback, %topic, status = %res.status(), "subscriber rejected update");
+            }
+            Err(e) => {
+                error!(callback = %sub.callback, %topic, %e, "failed to deliver update");
+            }
+        }
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}